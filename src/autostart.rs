@@ -0,0 +1,434 @@
+//! Programs started along with the window manager.
+//!
+//! Every [Command] is started from its own thread, after its delay, and waited on so
+//! that failing to start or exiting with an error ends up in the log. Supervised commands
+//! are started again when they exit, waiting longer after every restart, until they give
+//! up after too many of them. Their [Status] is shown in the bar, which is woken up with a
+//! client message on the root window whenever one of them changes.
+use log::{error, info};
+use penrose::{
+    core::{data_types::WinId, hooks::Hook, manager::WindowManager},
+    xcb::{XcbApi, XcbConnection},
+    PenroseError, Result,
+};
+use std::{
+    fs,
+    path::Path,
+    process::{self, Stdio},
+    sync::{Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Root window property marking that the window manager already ran on this X server.
+const STARTED: &str = "_PENROSE_AUTOSTARTED";
+/// Client message sent to the root window when the status of a daemon changes
+const STATUS_MESSAGE: &str = "_PENROSE_DAEMON_STATUS";
+
+/// Wait before the first restart, doubled on every one after it
+const BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// A daemon running for this long starts counting its restarts from zero again
+const STABLE: Duration = Duration::from_secs(120);
+/// How often daemons that are not our children are checked
+const POLL: Duration = Duration::from_secs(2);
+
+/// State of a supervised daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Running {
+        pid: u32,
+        restarts: u32,
+    },
+    /// Waiting to be started again
+    Restarting {
+        restarts: u32,
+    },
+    /// Exited more than its maximum number of restarts
+    Failed {
+        restarts: u32,
+    },
+}
+
+static STATUSES: Mutex<Vec<(String, Status)>> = Mutex::new(vec![]);
+
+/// Sends [STATUS_MESSAGE] from the supervising threads, which can't use penrose's connection.
+struct Waker {
+    conn: xcb::Connection,
+    root: WinId,
+    message: u32,
+}
+
+static WAKER: OnceLock<Waker> = OnceLock::new();
+
+impl Waker {
+    fn connect() -> Result<Self> {
+        let (conn, screen_i) = xcb::Connection::connect(None).map_err(raw)?;
+        let root = conn
+            .get_setup()
+            .roots()
+            .nth(screen_i as usize)
+            .map(|screen| screen.root())
+            .ok_or_else(|| PenroseError::Raw("no root window".into()))?;
+        let message = xcb::intern_atom(&conn, false, STATUS_MESSAGE)
+            .get_reply()
+            .map_err(raw)?
+            .atom();
+        Ok(Self {
+            conn,
+            root,
+            message,
+        })
+    }
+
+    fn wake(&self) {
+        let event = xcb::ClientMessageEvent::new(
+            32,
+            self.root,
+            self.message,
+            xcb::ClientMessageData::from_data32([0; 5]),
+        );
+        xcb::send_event(
+            &self.conn,
+            false,
+            self.root,
+            xcb::EVENT_MASK_SUBSTRUCTURE_REDIRECT | xcb::EVENT_MASK_SUBSTRUCTURE_NOTIFY,
+            &event,
+        );
+        self.conn.flush();
+    }
+}
+
+fn set_status(name: &str, status: Status) {
+    let changed = match STATUSES.lock() {
+        Ok(mut statuses) => match statuses.iter_mut().find(|(n, _)| n == name) {
+            Some((_, s)) if *s == status => false,
+            Some((_, s)) => {
+                *s = status;
+                true
+            }
+            None => {
+                statuses.push((name.to_string(), status));
+                true
+            }
+        },
+        Err(_) => false,
+    };
+    if let Some(waker) = WAKER.get().filter(|_| changed) {
+        waker.wake();
+    }
+}
+
+/// The status of every supervised daemon, by process name.
+pub fn daemon_statuses() -> Vec<(String, Status)> {
+    STATUSES.lock().map(|s| s.clone()).unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Run {
+    /// Every time the window manager starts
+    Always,
+    /// Only the first time the window manager starts on this X server
+    Once,
+    /// Every time the window manager starts, killing the instance left by the last one
+    Restart,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    program: String,
+    args: Vec<String>,
+    run: Run,
+    delay: Duration,
+    env: Vec<(String, String)>,
+    /// Process name to look for, the program's file name if not given
+    process: Option<String>,
+    unless_running: bool,
+    /// Restarts allowed in a row, if supervised
+    max_restarts: Option<u32>,
+}
+
+impl Command {
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: vec![],
+            run: Run::Always,
+            delay: Duration::from_secs(0),
+            env: vec![],
+            process: None,
+            unless_running: false,
+            max_restarts: None,
+        }
+    }
+
+    /// Run `command` through `sh -c`, for globs and `~`. The shell has no name of its own
+    /// to look for, so `process` is the name of what it runs.
+    pub fn shell(process: impl Into<String>, command: impl Into<String>) -> Self {
+        Self {
+            process: Some(process.into()),
+            ..Self::new("sh").args(&["-c", &command.into()])
+        }
+    }
+
+    pub fn args(mut self, args: &[&str]) -> Self {
+        self.args.extend(args.iter().map(|a| a.to_string()));
+        self
+    }
+
+    pub fn once(mut self) -> Self {
+        self.run = Run::Once;
+        self
+    }
+
+    pub fn restart(mut self) -> Self {
+        self.run = Run::Restart;
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Set `key` to `value` in the environment of the command.
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Skip the command while a process called `name` is running.
+    pub fn unless_running(mut self, name: impl Into<String>) -> Self {
+        self.process = Some(name.into());
+        self.unless_running = true;
+        self
+    }
+
+    /// Start the command again when it exits, giving up after `max_restarts` quick
+    /// restarts in a row. Daemons that fork are followed by their process name, when it
+    /// was given to [unless_running][Command::unless_running] or [shell][Command::shell].
+    pub fn supervise(mut self, max_restarts: u32) -> Self {
+        self.max_restarts = Some(max_restarts);
+        self
+    }
+
+    fn process_name(&self) -> &str {
+        match &self.process {
+            Some(name) => name,
+            None => Path::new(&self.program)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or(&self.program),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let mut s = self.program.clone();
+        self.args.iter().for_each(|a| {
+            s.push(' ');
+            s.push_str(a);
+        });
+        s
+    }
+
+    fn start(self) {
+        thread::spawn(move || {
+            thread::sleep(self.delay);
+            let name = self.process_name().to_string();
+            if self.run == Run::Restart {
+                stop(&name);
+            }
+            let running = pids_of(&name).first().copied();
+            if self.unless_running && running.is_some() {
+                info!("autostart: {} is already running", name);
+                if self.max_restarts.is_some() {
+                    self.supervise_from(&name, running);
+                }
+                return;
+            }
+
+            match self.max_restarts {
+                Some(_) => self.supervise_from(&name, None),
+                None => {
+                    self.run_once(None);
+                }
+            }
+        });
+    }
+
+    /// Run the command and wait for it, returning its pid if it could be started. The
+    /// status of supervised commands is updated with their number of `restarts`.
+    fn run_once(&self, restarts: Option<u32>) -> Option<u32> {
+        let mut child = match process::Command::new(&self.program)
+            .args(&self.args)
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                error!("autostart: unable to run `{}`: {}", self.describe(), e);
+                return None;
+            }
+        };
+        if let Some(restarts) = restarts {
+            let pid = child.id();
+            set_status(self.process_name(), Status::Running { pid, restarts });
+        }
+        match child.wait() {
+            Ok(status) if !status.success() => {
+                error!("autostart: `{}` exited with {}", self.describe(), status)
+            }
+            Ok(_) => (),
+            Err(e) => error!("autostart: unable to wait for `{}`: {}", self.describe(), e),
+        }
+        Some(child.id())
+    }
+
+    /// Keep the daemon alive, starting by watching `pid` if it is already running.
+    fn supervise_from(&self, name: &str, mut pid: Option<u32>) {
+        let max_restarts = self.max_restarts.unwrap_or(0);
+        let mut restarts = 0;
+        loop {
+            let started = Instant::now();
+            match pid.take() {
+                Some(pid) => {
+                    set_status(name, Status::Running { pid, restarts });
+                    while Path::new(&format!("/proc/{}", pid)).exists() {
+                        thread::sleep(POLL);
+                    }
+                }
+                None => {
+                    // the daemon might have forked and left its parent, which can only be told
+                    // apart from other processes when we were told what it is called
+                    if self.run_once(Some(restarts)).is_some() && self.process.is_some() {
+                        if let Some(forked) = pids_of(name).first() {
+                            pid = Some(*forked);
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            if started.elapsed() >= STABLE {
+                restarts = 0;
+            }
+            if restarts >= max_restarts {
+                error!("autostart: {} exited too many times, giving up", name);
+                set_status(name, Status::Failed { restarts });
+                return;
+            }
+            let backoff = (BACKOFF * 2u32.saturating_pow(restarts)).min(MAX_BACKOFF);
+            restarts += 1;
+            error!("autostart: {} exited, restarting in {:?}", name, backoff);
+            set_status(name, Status::Restarting { restarts });
+            thread::sleep(backoff);
+        }
+    }
+}
+
+/// Ids of the processes called `name`, as seen in `/proc/<pid>/comm`.
+pub fn pids_of(name: &str) -> Vec<u32> {
+    // comm is truncated to 15 bytes
+    let name = &name.as_bytes()[..name.len().min(15)];
+    let own = process::id();
+    fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok()?.file_name().to_str()?.parse::<u32>().ok())
+                .filter(|pid| *pid != own)
+                .filter(|pid| {
+                    fs::read(format!("/proc/{}/comm", pid))
+                        .map(|comm| comm.strip_suffix(b"\n").unwrap_or(&comm) == name)
+                        .unwrap_or(false)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Kill the processes called `name` and give them a moment to go away.
+fn stop(name: &str) {
+    let pids: Vec<String> = pids_of(name).iter().map(|p| p.to_string()).collect();
+    if pids.is_empty() {
+        return;
+    }
+    if let Err(e) = process::Command::new("kill").args(&pids).status() {
+        error!("autostart: unable to stop {}: {}", name, e);
+        return;
+    }
+    let start = Instant::now();
+    while !pids_of(name).is_empty() && start.elapsed() < Duration::from_secs(1) {
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn raw(e: impl std::fmt::Debug) -> PenroseError {
+    PenroseError::Raw(format!("{:?}", e))
+}
+
+/// Whether this is the first start on the X server, marking it as started if it is.
+fn first_start(conn: &xcb::Connection, root: WinId) -> Result<bool> {
+    let atom = xcb::intern_atom(conn, false, STARTED)
+        .get_reply()
+        .map_err(raw)?
+        .atom();
+
+    let started = xcb::get_property(conn, false, root, atom, xcb::ATOM_CARDINAL, 0, 1)
+        .get_reply()
+        .map_err(raw)?
+        .value_len()
+        > 0;
+    if !started {
+        xcb::change_property(
+            conn,
+            xcb::PROP_MODE_REPLACE as u8,
+            root,
+            atom,
+            xcb::ATOM_CARDINAL,
+            32,
+            &[1u32],
+        );
+        conn.flush();
+    }
+    Ok(!started)
+}
+
+/// Starts the declared [Command]s when the window manager starts.
+pub struct Autostart {
+    commands: Vec<Command>,
+}
+
+impl Autostart {
+    pub fn new(commands: Vec<Command>) -> Box<Self> {
+        Box::new(Self { commands })
+    }
+}
+
+// the mark is set on the root window through penrose's own connection
+impl Hook<XcbConnection> for Autostart {
+    fn startup(&mut self, wm: &mut WindowManager<XcbConnection>) -> Result<()> {
+        let root = wm.conn().api().root();
+        let first = first_start(wm.xcb_connection(), root).unwrap_or_else(|e| {
+            error!("autostart: unable to tell if this is a restart: {}", e);
+            true
+        });
+        if self.commands.iter().any(|c| c.max_restarts.is_some()) && WAKER.get().is_none() {
+            match Waker::connect() {
+                Ok(waker) => {
+                    let _ = WAKER.set(waker);
+                }
+                Err(e) => error!(
+                    "autostart: daemon changes will wait for other events: {}",
+                    e
+                ),
+            }
+        }
+        self.commands
+            .iter()
+            .filter(|c| first || c.run != Run::Once)
+            .for_each(|c| c.clone().start());
+        Ok(())
+    }
+}
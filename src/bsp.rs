@@ -0,0 +1,408 @@
+use penrose::core::{
+    client::Client,
+    data_types::{Change, Region, ResizeAction, WinId},
+};
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::layouts::{split_off, Side};
+
+enum Node {
+    Leaf(WinId),
+    Split {
+        splits_width: bool,
+        ratio: f32,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    fn contains(&self, id: WinId) -> bool {
+        match self {
+            Node::Leaf(leaf) => *leaf == id,
+            Node::Split { first, second, .. } => first.contains(id) || second.contains(id),
+        }
+    }
+
+    fn is_leaf(&self, id: WinId) -> bool {
+        matches!(self, Node::Leaf(leaf) if *leaf == id)
+    }
+
+    fn last_leaf(&self) -> WinId {
+        match self {
+            Node::Leaf(leaf) => *leaf,
+            Node::Split { second, .. } => second.last_leaf(),
+        }
+    }
+
+    fn halves(&self, region: &Region) -> (Region, Region) {
+        match self {
+            Node::Split {
+                splits_width: true,
+                ratio,
+                ..
+            } => split_off(region, Side::Left, *ratio),
+            Node::Split { ratio, .. } => split_off(region, Side::Top, *ratio),
+            Node::Leaf(_) => (*region, *region),
+        }
+    }
+
+    fn arrange(&self, region: &Region, actions: &mut Vec<ResizeAction>) {
+        match self {
+            Node::Leaf(id) => actions.push((*id, Some(*region))),
+            Node::Split { first, second, .. } => {
+                let (r1, r2) = self.halves(region);
+                first.arrange(&r1, actions);
+                second.arrange(&r2, actions);
+            }
+        }
+    }
+
+    fn region_of(&self, id: WinId, region: &Region) -> Option<Region> {
+        match self {
+            Node::Leaf(leaf) if *leaf == id => Some(*region),
+            Node::Leaf(_) => None,
+            Node::Split { first, second, .. } => {
+                let (r1, r2) = self.halves(region);
+                first
+                    .region_of(id, &r1)
+                    .or_else(|| second.region_of(id, &r2))
+            }
+        }
+    }
+
+    fn insert(self, target: WinId, id: WinId, splits_width: bool, new_first: bool) -> Node {
+        match self {
+            Node::Leaf(leaf) if leaf == target => {
+                let (first, second) = if new_first {
+                    (Node::Leaf(id), Node::Leaf(leaf))
+                } else {
+                    (Node::Leaf(leaf), Node::Leaf(id))
+                };
+                Node::Split {
+                    splits_width,
+                    ratio: 0.5,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                }
+            }
+            Node::Leaf(_) => self,
+            Node::Split {
+                splits_width: sw,
+                ratio,
+                first,
+                second,
+            } => Node::Split {
+                splits_width: sw,
+                ratio,
+                first: Box::new(first.insert(target, id, splits_width, new_first)),
+                second: Box::new(second.insert(target, id, splits_width, new_first)),
+            },
+        }
+    }
+
+    fn retain(self, ids: &[WinId]) -> Option<Node> {
+        match self {
+            Node::Leaf(leaf) if !ids.contains(&leaf) => None,
+            Node::Leaf(_) => Some(self),
+            Node::Split {
+                splits_width,
+                ratio,
+                first,
+                second,
+            } => match (first.retain(ids), second.retain(ids)) {
+                (Some(first), Some(second)) => Some(Node::Split {
+                    splits_width,
+                    ratio,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                }),
+                (Some(node), None) | (None, Some(node)) => Some(node),
+                (None, None) => None,
+            },
+        }
+    }
+
+    /// Whether the split holding the leaf for `id` splits the width, if `id` has a parent.
+    fn parent_splits_width(&self, id: WinId) -> Option<bool> {
+        match self {
+            Node::Leaf(_) => None,
+            Node::Split {
+                splits_width,
+                first,
+                second,
+                ..
+            } => {
+                if first.is_leaf(id) || second.is_leaf(id) {
+                    Some(*splits_width)
+                } else {
+                    first
+                        .parent_splits_width(id)
+                        .or_else(|| second.parent_splits_width(id))
+                }
+            }
+        }
+    }
+
+    /// The split holding the leaf for `id` as one of its direct children.
+    fn parent_mut(&mut self, id: WinId) -> Option<&mut Node> {
+        let is_parent = match self {
+            Node::Leaf(_) => return None,
+            Node::Split { first, second, .. } => first.is_leaf(id) || second.is_leaf(id),
+        };
+        if is_parent {
+            return Some(self);
+        }
+        match self {
+            Node::Leaf(_) => None,
+            Node::Split { first, second, .. } => match first.parent_mut(id) {
+                Some(node) => Some(node),
+                None => second.parent_mut(id),
+            },
+        }
+    }
+
+    fn rotate(&mut self) {
+        if let Node::Split {
+            splits_width,
+            ratio,
+            first,
+            second,
+        } = self
+        {
+            // Turning clockwise keeps left/right as top/bottom, but top/bottom become right/left
+            if !*splits_width {
+                std::mem::swap(first, second);
+                *ratio = 1.0 - *ratio;
+            }
+            *splits_width = !*splits_width;
+            first.rotate();
+            second.rotate();
+        }
+    }
+
+    fn flip(&mut self, horizontally: bool) {
+        if let Node::Split {
+            splits_width,
+            ratio,
+            first,
+            second,
+        } = self
+        {
+            if *splits_width == horizontally {
+                std::mem::swap(first, second);
+                *ratio = 1.0 - *ratio;
+            }
+            first.flip(horizontally);
+            second.flip(horizontally);
+        }
+    }
+}
+
+/// How a tree picks the direction a client is split in when no side has been preselected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Split {
+    /// Along the longest side of the client, as bspwm does, for [bsp]
+    Longest,
+    /// Across the split the client is part of, as dwindle does, for [focus_dwindle]
+    Alternate,
+}
+
+/// The tree of a workspace, by its index, kept for one of the two layouts.
+pub type TreeKey = (usize, Split);
+
+#[derive(Default)]
+struct Tree {
+    root: Option<Node>,
+    last_focused: Option<WinId>,
+    presel: Option<(WinId, Side)>,
+}
+
+impl Tree {
+    fn contains(&self, id: WinId) -> bool {
+        self.root.as_ref().map(|r| r.contains(id)).unwrap_or(false)
+    }
+
+    fn insert(&mut self, id: WinId, region: &Region, split: Split) {
+        let root = match self.root.take() {
+            Some(root) => root,
+            None => {
+                self.root = Some(Node::Leaf(id));
+                return;
+            }
+        };
+
+        let (target, side) = match self.presel.take() {
+            Some((target, side)) if root.contains(target) => (target, Some(side)),
+            _ => match self.last_focused {
+                Some(target) if root.contains(target) => (target, None),
+                _ => (root.last_leaf(), None),
+            },
+        };
+
+        let (splits_width, new_first) = match side {
+            Some(Side::Left) => (true, true),
+            Some(Side::Right) => (true, false),
+            Some(Side::Top) => (false, true),
+            Some(Side::Bottom) => (false, false),
+            None if split == Split::Alternate => {
+                (!root.parent_splits_width(target).unwrap_or(false), false)
+            }
+            None => {
+                let r = root.region_of(target, region).unwrap_or(*region);
+                (r.w >= r.h, false)
+            }
+        };
+
+        self.root = Some(root.insert(target, id, splits_width, new_first));
+    }
+
+    /// Drop clients that are no longer on the workspace and split in the new ones.
+    fn sync(&mut self, ids: &[WinId], region: &Region, split: Split) {
+        self.root = self.root.take().and_then(|r| r.retain(ids));
+        for id in ids {
+            if !self.contains(*id) {
+                self.insert(*id, region, split);
+            }
+        }
+    }
+}
+
+thread_local! {
+    static TREES: RefCell<HashMap<TreeKey, Tree>> = RefCell::new(HashMap::new());
+}
+
+/// Run `f` on the tree `key` if it holds `id`.
+fn with_tree<F: FnOnce(&mut Tree)>(key: TreeKey, id: WinId, f: F) {
+    TREES.with(|t| {
+        if let Some(tree) = t
+            .borrow_mut()
+            .get_mut(&key)
+            .filter(|tree| tree.contains(id))
+        {
+            f(tree)
+        }
+    })
+}
+
+/// Make `id` the client the next one splits, in every tree holding it. Focusing a client
+/// doesn't lay out its workspace again, so the trees are told about it from
+/// [BspFocus][crate::hooks::BspFocus].
+pub fn bsp_focus(id: WinId) {
+    TREES.with(|t| {
+        t.borrow_mut()
+            .values_mut()
+            .filter(|tree| tree.contains(id))
+            .for_each(|tree| tree.last_focused = Some(id))
+    })
+}
+
+/// Place the next client of the tree `key` against `side` of `id`. Preselecting the
+/// same side again cancels the preselection.
+pub fn bsp_preselect(key: TreeKey, id: WinId, side: Side) {
+    with_tree(key, id, |tree| {
+        tree.presel = match tree.presel {
+            Some(presel) if presel == (id, side) => None,
+            _ => Some((id, side)),
+        }
+    })
+}
+
+/// Rotate the subtree holding `id` in the tree `key` by 90 degrees clockwise.
+pub fn bsp_rotate(key: TreeKey, id: WinId) {
+    with_tree(key, id, |tree| {
+        if let Some(node) = tree.root.as_mut().and_then(|r| r.parent_mut(id)) {
+            node.rotate()
+        }
+    })
+}
+
+/// Mirror the subtree holding `id` in the tree `key`, left to right when `horizontally`
+/// or else top to bottom.
+pub fn bsp_flip(key: TreeKey, id: WinId, horizontally: bool) {
+    with_tree(key, id, |tree| {
+        if let Some(node) = tree.root.as_mut().and_then(|r| r.parent_mut(id)) {
+            node.flip(horizontally)
+        }
+    })
+}
+
+/// Grow or shrink the client `id` by moving the ratio of the split that holds it in the
+/// tree `key`.
+pub fn bsp_resize(key: TreeKey, id: WinId, change: Change, step: f32) {
+    with_tree(key, id, |tree| {
+        if let Some(Node::Split { ratio, first, .. }) =
+            tree.root.as_mut().and_then(|r| r.parent_mut(id))
+        {
+            let step = if first.is_leaf(id) { step } else { -step };
+            let step = if change == Change::More { step } else { -step };
+            *ratio = (*ratio + step).clamp(0.1, 0.9);
+        }
+    })
+}
+
+fn arrange_tree(
+    clients: &[&Client],
+    focused: Option<WinId>,
+    monitor_region: &Region,
+    split: Split,
+) -> Vec<ResizeAction> {
+    let wix = match clients.first() {
+        Some(c) => c.workspace(),
+        None => return vec![],
+    };
+    let ids: Vec<WinId> = clients.iter().map(|c| c.id()).collect();
+
+    TREES.with(|t| {
+        let mut trees = t.borrow_mut();
+        let tree = trees.entry((wix, split)).or_default();
+        tree.sync(&ids, monitor_region, split);
+        if let Some(id) = focused.filter(|id| tree.contains(*id)) {
+            tree.last_focused = Some(id);
+        }
+
+        let mut actions = vec![];
+        if let Some(root) = &tree.root {
+            root.arrange(monitor_region, &mut actions);
+        }
+        actions
+    })
+}
+
+/**
+ * A layout in the style of bspwm.
+ *
+ * Every workspace keeps a binary tree of splits. A new client splits the region of the
+ * last focused client (or the preselected one, see [bsp_preselect]) along its longest
+ * side, and clients that go away hand their space back to their sibling. `max_main` and
+ * `ratio` are ignored: the tree stores a ratio per split instead.
+ */
+pub fn bsp(
+    clients: &[&Client],
+    focused: Option<WinId>,
+    monitor_region: &Region,
+    _: u32,
+    _: f32,
+) -> Vec<ResizeAction> {
+    arrange_tree(clients, focused, monitor_region, Split::Longest)
+}
+
+/**
+ * A [dwindle][crate::layouts::dwindle] that splits the region of the focused client
+ * instead of the last one, so new clients open next to the one being worked on. The
+ * focus is followed through [BspFocus][crate::hooks::BspFocus], as this layout doesn't
+ * run again when it changes.
+ *
+ * Splits alternate between left/right and top/bottom like in dwindle, with the new client
+ * on the right or at the bottom. It shares the tree operations of [bsp], so preselection,
+ * rotation, flipping and resizing work the same.
+ */
+pub fn focus_dwindle(
+    clients: &[&Client],
+    focused: Option<WinId>,
+    monitor_region: &Region,
+    _: u32,
+    _: f32,
+) -> Vec<ResizeAction> {
+    arrange_tree(clients, focused, monitor_region, Split::Alternate)
+}
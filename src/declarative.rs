@@ -0,0 +1,390 @@
+//! Layouts described in a TOML file instead of Rust, e.g.
+//!
+//! ```toml
+//! [[layout]]
+//! symbol = "[tall]"
+//! ratio = 0.6
+//! overflow = "last"
+//!
+//! [layout.root]
+//! split = "columns"
+//! sizes = ["ratio", "inverse_ratio"]
+//! children = [
+//!     { slot = "max_main" },
+//!     { split = "rows", sizes = [2, 1], children = [{ slot = 1 }, { slot = "rest", arrange = "grid" }] },
+//! ]
+//! ```
+//!
+//! A layout is a tree of splits whose leaves are slots. Clients fill the slots in order,
+//! each slot taking a fixed number of clients, `max_main` of them or the `rest`. Slots
+//! without clients give their space back to their siblings.
+use penrose::{
+    core::{
+        client::Client,
+        data_types::{Region, ResizeAction, WinId},
+        layout::{Layout, LayoutConf, LayoutFunc},
+    },
+    PenroseError,
+};
+use serde::Deserialize;
+use std::{cell::RefCell, fs, path::Path};
+
+use crate::layouts::{grid_regions, weights_of, EvenSplit};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Rows,
+    Columns,
+}
+
+/// How the clients of a single slot share its region.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Arrange {
+    #[default]
+    Rows,
+    Columns,
+    Grid,
+    /// Every client takes the whole slot, monocle-style
+    Stack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamedCount {
+    /// The `max_main` of the layout, changed with `update_max_main`
+    MaxMain,
+    /// Every client left
+    Rest,
+}
+
+/// How many clients a slot takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Count {
+    Fixed(u32),
+    Named(NamedCount),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamedSize {
+    /// The `ratio` of the layout, changed with `update_main_ratio`
+    Ratio,
+    /// One minus the `ratio` of the layout
+    InverseRatio,
+}
+
+/// The share of a split that a child gets, relative to its siblings.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Size {
+    Weight(f32),
+    Named(NamedSize),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Node {
+    Split {
+        split: Direction,
+        #[serde(default)]
+        sizes: Vec<Size>,
+        children: Vec<Node>,
+    },
+    Slot {
+        slot: Count,
+        #[serde(default)]
+        arrange: Arrange,
+    },
+}
+
+/// What happens to the clients that don't fit in any slot.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    /// They join the last slot holding clients
+    #[default]
+    Last,
+    /// They are unmapped
+    Hide,
+}
+
+fn default_max_main() -> u32 {
+    1
+}
+
+fn default_ratio() -> f32 {
+    0.6
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Definition {
+    pub symbol: String,
+    #[serde(default = "default_max_main")]
+    pub max_main: u32,
+    #[serde(default = "default_ratio")]
+    pub ratio: f32,
+    #[serde(default)]
+    pub follow_focus: bool,
+    #[serde(default)]
+    pub overflow: Overflow,
+    pub root: Node,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayoutsFile {
+    #[serde(default)]
+    layout: Vec<Definition>,
+}
+
+impl Node {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Node::Split {
+                sizes, children, ..
+            } => {
+                if children.is_empty() {
+                    return Err("a split needs at least one child".into());
+                }
+                if !sizes.is_empty() && sizes.len() != children.len() {
+                    return Err(format!(
+                        "a split has {} sizes for {} children",
+                        sizes.len(),
+                        children.len()
+                    ));
+                }
+                if sizes
+                    .iter()
+                    .any(|s| matches!(s, Size::Weight(w) if w.is_nan() || *w <= 0.0))
+                {
+                    return Err("split sizes have to be positive".into());
+                }
+                children.iter().try_for_each(|c| c.validate())
+            }
+            Node::Slot { .. } => Ok(()),
+        }
+    }
+
+    /// How many clients each slot takes, in order, out of `remaining`.
+    fn slot_counts(&self, remaining: &mut usize, max_main: u32, counts: &mut Vec<usize>) {
+        match self {
+            Node::Split { children, .. } => children
+                .iter()
+                .for_each(|c| c.slot_counts(remaining, max_main, counts)),
+            Node::Slot { slot, .. } => {
+                let n = match slot {
+                    Count::Fixed(n) => (*n as usize).min(*remaining),
+                    Count::Named(NamedCount::MaxMain) => (max_main as usize).min(*remaining),
+                    Count::Named(NamedCount::Rest) => *remaining,
+                };
+                *remaining -= n;
+                counts.push(n);
+            }
+        }
+    }
+
+    /// Number of clients in the subtree, moving `cursor` past its slots.
+    fn total(&self, counts: &[usize], cursor: &mut usize) -> usize {
+        match self {
+            Node::Split { children, .. } => children.iter().map(|c| c.total(counts, cursor)).sum(),
+            Node::Slot { .. } => {
+                *cursor += 1;
+                counts[*cursor - 1]
+            }
+        }
+    }
+
+    /// Arrange the first clients in `region`, returning the clients left for later slots.
+    fn place<'a, 'c>(
+        &self,
+        region: &Region,
+        clients: &'a [&'c Client],
+        counts: &[usize],
+        cursor: &mut usize,
+        ratio: f32,
+        actions: &mut Vec<ResizeAction>,
+    ) -> &'a [&'c Client] {
+        match self {
+            Node::Slot { arrange, .. } => {
+                let (mine, rest) = clients.split_at(counts[*cursor]);
+                *cursor += 1;
+                let regions = match arrange {
+                    Arrange::Rows => region.rows_weighted(&weights_of(mine)),
+                    Arrange::Columns => region.columns_weighted(&weights_of(mine)),
+                    Arrange::Grid => grid_regions(region, mine.len() as u32),
+                    Arrange::Stack => vec![*region; mine.len()],
+                };
+                actions.extend(mine.iter().zip(regions).map(|(c, r)| (c.id(), Some(r))));
+                rest
+            }
+
+            Node::Split {
+                split,
+                sizes,
+                children,
+            } => {
+                let mut peek = *cursor;
+                let totals: Vec<usize> = children
+                    .iter()
+                    .map(|c| c.total(counts, &mut peek))
+                    .collect();
+                let weights: Vec<f32> = children
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| totals[*i] > 0)
+                    .map(|(i, _)| match sizes.get(i) {
+                        Some(Size::Weight(w)) => *w,
+                        Some(Size::Named(NamedSize::Ratio)) => ratio,
+                        Some(Size::Named(NamedSize::InverseRatio)) => 1.0 - ratio,
+                        None => 1.0,
+                    })
+                    .collect();
+                let mut regions = match split {
+                    Direction::Rows => region.rows_weighted(&weights),
+                    Direction::Columns => region.columns_weighted(&weights),
+                }
+                .into_iter();
+
+                let mut clients = clients;
+                for (child, total) in children.iter().zip(totals) {
+                    if total == 0 {
+                        child.total(counts, cursor);
+                        continue;
+                    }
+                    if let Some(r) = regions.next() {
+                        clients = child.place(&r, clients, counts, cursor, ratio, actions);
+                    }
+                }
+                clients
+            }
+        }
+    }
+}
+
+impl Definition {
+    fn arrange(
+        &self,
+        clients: &[&Client],
+        region: &Region,
+        max_main: u32,
+        ratio: f32,
+    ) -> Vec<ResizeAction> {
+        let mut remaining = clients.len();
+        let mut counts = vec![];
+        self.root.slot_counts(&mut remaining, max_main, &mut counts);
+        if remaining > 0 && self.overflow == Overflow::Last {
+            let last = counts
+                .iter()
+                .rposition(|n| *n > 0)
+                .or(counts.len().checked_sub(1));
+            if let Some(i) = last {
+                counts[i] += remaining;
+            }
+        }
+
+        let mut actions = vec![];
+        let hidden = self
+            .root
+            .place(region, clients, &counts, &mut 0, ratio, &mut actions);
+        actions.extend(hidden.iter().map(|c| (c.id(), None)));
+        actions
+    }
+}
+
+thread_local! {
+    static DEFINITIONS: RefCell<Vec<Definition>> = const { RefCell::new(vec![]) };
+}
+
+/// Arrange `clients` with the registered definition at `index`.
+pub(crate) fn arrange_definition(
+    index: usize,
+    clients: &[&Client],
+    monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    DEFINITIONS.with(|d| {
+        d.borrow()
+            .get(index)
+            .map(|def| def.arrange(clients, monitor_region, max_main, ratio))
+            .unwrap_or_default()
+    })
+}
+
+// Layouts are plain function pointers, so every definition gets its own function that
+// looks the definition up by index.
+fn declared<const I: usize>(
+    clients: &[&Client],
+    _: Option<WinId>,
+    monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    arrange_definition(I, clients, monitor_region, max_main, ratio)
+}
+
+const DECLARED: [LayoutFunc; 8] = [
+    gapped!(transformed!(declared::<0>)),
+    gapped!(transformed!(declared::<1>)),
+    gapped!(transformed!(declared::<2>)),
+    gapped!(transformed!(declared::<3>)),
+    gapped!(transformed!(declared::<4>)),
+    gapped!(transformed!(declared::<5>)),
+    gapped!(transformed!(declared::<6>)),
+    gapped!(transformed!(declared::<7>)),
+];
+
+/// Parse the `[[layout]]` entries of a layouts file.
+pub fn parse_layouts(s: &str) -> penrose::Result<Vec<Definition>> {
+    let file: LayoutsFile = toml::from_str(s).map_err(|e| PenroseError::Raw(e.to_string()))?;
+    for def in file.layout.iter() {
+        def.root
+            .validate()
+            .map_err(|e| PenroseError::Raw(format!("layout {}: {}", def.symbol, e)))?;
+    }
+    Ok(file.layout)
+}
+
+/// Make `definitions` available as layouts. Every one of them needs its own function, so
+/// declaring more than there are is an error naming the ones left out.
+pub fn register_layouts(definitions: Vec<Definition>) -> penrose::Result<Vec<Layout>> {
+    if definitions.len() > DECLARED.len() {
+        let ignored: Vec<&str> = definitions[DECLARED.len()..]
+            .iter()
+            .map(|def| def.symbol.as_str())
+            .collect();
+        return Err(PenroseError::Raw(format!(
+            "only {} declarative layouts are supported, {} would be ignored",
+            DECLARED.len(),
+            ignored.join(", ")
+        )));
+    }
+
+    let layouts = definitions
+        .iter()
+        .zip(DECLARED.iter())
+        .map(|(def, f)| {
+            let conf = LayoutConf {
+                follow_focus: def.follow_focus,
+                ..LayoutConf::default()
+            };
+            Layout::new(def.symbol.clone(), conf, *f, def.max_main, def.ratio)
+        })
+        .collect();
+    DEFINITIONS.with(|d| *d.borrow_mut() = definitions);
+    Ok(layouts)
+}
+
+/// Load and register the layouts in the file at `path`, if there is one.
+pub fn load_layouts(path: impl AsRef<Path>) -> penrose::Result<Vec<Layout>> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let s = fs::read_to_string(path).map_err(|e| PenroseError::Raw(e.to_string()))?;
+    register_layouts(parse_layouts(&s)?)
+}
@@ -0,0 +1,329 @@
+//! Sizing of floating windows: what the window asks for in its `WM_NORMAL_HINTS`, bounded
+//! and shaped by what its [Rule][1] says, unless a window of the same class was moved
+//! somewhere else before, see [Geometries]. Where it ends up is its [Placement].
+//!
+//! [1]: crate::rules::Rule
+use penrose::{
+    core::data_types::{Point, Region, WinId},
+    PenroseError, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::PathBuf};
+
+/// Size given by a rule.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Size {
+    /// This fraction of the screen, in both directions
+    Scale(f64),
+    /// In pixels
+    Absolute(u32, u32),
+}
+
+/// Where a floating window is put.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Placement {
+    /// Centered on the screen showing its workspace
+    #[default]
+    Screen,
+    /// Centered on the screen under the pointer
+    PointerScreen,
+    /// Centered over the window it is transient for, on the screen of that window
+    Parent,
+    /// Centered under the pointer, on the screen under it
+    Pointer,
+}
+
+/// How big a floating window is made.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloatSize {
+    pub size: Size,
+    /// Use the size from the window's size hints instead of `size` when it has one
+    pub hints: bool,
+    pub min: Option<(u32, u32)>,
+    pub max: Option<(u32, u32)>,
+    /// Keep the shape the window was created with, fitting it inside `size`
+    pub keep_aspect: bool,
+}
+
+impl FloatSize {
+    pub fn scale(scale: f64) -> Self {
+        Self {
+            size: Size::Scale(scale),
+            hints: false,
+            min: None,
+            max: None,
+            keep_aspect: false,
+        }
+    }
+
+    pub fn absolute(w: u32, h: u32) -> Self {
+        Self {
+            size: Size::Absolute(w, h),
+            ..Self::scale(1.0)
+        }
+    }
+
+    pub fn with_hints(mut self) -> Self {
+        self.hints = true;
+        self
+    }
+
+    pub fn min(mut self, w: u32, h: u32) -> Self {
+        self.min = Some((w, h));
+        self
+    }
+
+    pub fn max(mut self, w: u32, h: u32) -> Self {
+        self.max = Some((w, h));
+        self
+    }
+
+    pub fn keep_aspect(mut self) -> Self {
+        self.keep_aspect = true;
+        self
+    }
+}
+
+/// The parts of `WM_NORMAL_HINTS` used for sizing.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SizeHints {
+    pub size: Option<(u32, u32)>,
+    pub min: Option<(u32, u32)>,
+    pub max: Option<(u32, u32)>,
+    /// Smallest and largest width / height allowed
+    pub aspect: Option<(f64, f64)>,
+}
+
+const US_SIZE: u32 = 1 << 1;
+const P_SIZE: u32 = 1 << 3;
+const P_MIN_SIZE: u32 = 1 << 4;
+const P_MAX_SIZE: u32 = 1 << 5;
+const P_ASPECT: u32 = 1 << 7;
+
+impl SizeHints {
+    /// Read the hints from the 18 values of a `WM_SIZE_HINTS` property.
+    pub fn parse(data: &[u32]) -> Self {
+        if data.len() < 15 {
+            return Self::default();
+        }
+        let flags = data[0];
+        let pair = |flag: u32, i: usize| {
+            Some((data[i], data[i + 1])).filter(|(w, h)| flags & flag != 0 && *w > 0 && *h > 0)
+        };
+        let ratio = |(n, d): (u32, u32)| n as f64 / d as f64;
+
+        Self {
+            size: pair(US_SIZE | P_SIZE, 3),
+            min: pair(P_MIN_SIZE, 5),
+            max: pair(P_MAX_SIZE, 7),
+            aspect: pair(P_ASPECT, 11)
+                .zip(pair(P_ASPECT, 13))
+                .map(|(min, max)| (ratio(min), ratio(max))),
+        }
+    }
+
+    /// Ask the X server for the size hints of `id`.
+    pub fn of(conn: &xcb::Connection, id: WinId) -> Self {
+        let reply = xcb::get_property(
+            conn,
+            false,
+            id,
+            xcb::ATOM_WM_NORMAL_HINTS,
+            xcb::ATOM_WM_SIZE_HINTS,
+            0,
+            18,
+        )
+        .get_reply();
+        match reply {
+            Ok(r) if r.format() == 32 => Self::parse(r.value()),
+            _ => Self::default(),
+        }
+    }
+}
+
+/// The window `id` is transient for, from its `WM_TRANSIENT_FOR`.
+pub fn transient_for(conn: &xcb::Connection, id: WinId) -> Option<WinId> {
+    let reply = xcb::get_property(
+        conn,
+        false,
+        id,
+        xcb::ATOM_WM_TRANSIENT_FOR,
+        xcb::ATOM_WINDOW,
+        0,
+        1,
+    )
+    .get_reply()
+    .ok()
+    .filter(|r| r.format() == 32)?;
+    reply.value::<u32>().first().copied().filter(|id| *id != 0)
+}
+
+fn fit_aspect((w, h): (u32, u32), (min, max): (f64, f64)) -> (u32, u32) {
+    let ratio = w as f64 / h.max(1) as f64;
+    if ratio < min {
+        (w, (w as f64 / min).round() as u32)
+    } else if ratio > max {
+        ((h as f64 * max).round() as u32, h)
+    } else {
+        (w, h)
+    }
+}
+
+/**
+ * Where a floating window of `current` size goes, centered on `screen`. The size from
+ * the hints, if the rule allows it, or the one from the rule is bounded by the minimum
+ * and maximum of both and made to fit the aspect ratio of the hints, or of `current`
+ * when the rule keeps it. The window never ends up bigger than the screen.
+ */
+pub fn float_region(
+    size: &FloatSize,
+    hints: &SizeHints,
+    current: Option<(u32, u32)>,
+    screen: &Region,
+) -> Region {
+    let (mut w, mut h) = match (hints.size, size.size) {
+        (Some(s), _) if size.hints => s,
+        (_, Size::Scale(scale)) => (
+            (screen.w as f64 * scale) as u32,
+            (screen.h as f64 * scale) as u32,
+        ),
+        (_, Size::Absolute(w, h)) => (w, h),
+    };
+
+    let kept = current
+        .filter(|(w, h)| size.keep_aspect && *w > 0 && *h > 0)
+        .map(|(w, h)| w as f64 / h as f64);
+    if let Some(aspect) = hints.aspect.or(kept.map(|r| (r, r))) {
+        (w, h) = fit_aspect((w, h), aspect);
+    }
+
+    let (mut min_w, mut min_h) = hints.min.unwrap_or((1, 1));
+    if let Some((rw, rh)) = size.min {
+        min_w = min_w.max(rw);
+        min_h = min_h.max(rh);
+    }
+    let (mut max_w, mut max_h) = hints.max.unwrap_or((u32::MAX, u32::MAX));
+    if let Some((rw, rh)) = size.max {
+        max_w = max_w.min(rw);
+        max_h = max_h.min(rh);
+    }
+    w = w.max(min_w).min(max_w).min(screen.w);
+    h = h.max(min_h).min(max_h).min(screen.h);
+
+    Region::new(
+        screen.x + (screen.w - w) / 2,
+        screen.y + (screen.h - h) / 2,
+        w,
+        h,
+    )
+}
+
+/// `region` moved so that its middle is at `point`, as far as it fits inside `screen`.
+pub fn center_on(region: Region, point: Point, screen: &Region) -> Region {
+    let (w, h) = (region.w.min(screen.w), region.h.min(screen.h));
+    let x = point.x.saturating_sub(w / 2);
+    let y = point.y.saturating_sub(h / 2);
+    Region::new(
+        x.clamp(screen.x, screen.x + screen.w - w),
+        y.clamp(screen.y, screen.y + screen.h - h),
+        w,
+        h,
+    )
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GeometriesFile {
+    /// `[x, y, w, h]` by class, relative to the screen
+    #[serde(default)]
+    geometry: HashMap<String, [u32; 4]>,
+}
+
+/**
+ * The last geometry of floating windows by class, kept in a state file. Only windows
+ * the user moved or resized away from where they were [placed][Geometries::place] are
+ * kept, so that the rules still decide for the others.
+ */
+pub struct Geometries {
+    state_file: PathBuf,
+    file: GeometriesFile,
+    /// Class of the placed windows and where they are
+    placed: HashMap<WinId, (String, Region)>,
+}
+
+impl Geometries {
+    pub fn new(state_file: impl Into<PathBuf>) -> Self {
+        Self {
+            state_file: state_file.into(),
+            file: GeometriesFile::default(),
+            placed: HashMap::new(),
+        }
+    }
+
+    pub fn load(&mut self) -> Result<()> {
+        if !self.state_file.exists() {
+            return Ok(());
+        }
+        let s =
+            fs::read_to_string(&self.state_file).map_err(|e| PenroseError::Raw(e.to_string()))?;
+        self.file = toml::from_str(&s).map_err(|e| PenroseError::Raw(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Where the last window of `class` was, moved inside `screen` if it doesn't fit.
+    pub fn get(&self, class: &str, screen: &Region) -> Option<Region> {
+        let [x, y, w, h] = *self.file.geometry.get(class)?;
+        let (w, h) = (w.min(screen.w), h.min(screen.h));
+        Some(Region::new(
+            screen.x + x.min(screen.w - w),
+            screen.y + y.min(screen.h - h),
+            w,
+            h,
+        ))
+    }
+
+    /// Follow the window `id` of `class`, just placed at `region`.
+    pub fn place(&mut self, id: WinId, class: &str, region: Region) {
+        self.placed.insert(id, (class.to_string(), region));
+    }
+
+    pub fn forget(&mut self, id: WinId) {
+        self.placed.remove(&id);
+    }
+
+    /// The placed window `id` is now at `region` on `screen`, which is kept for its class
+    /// if it isn't where the window already was.
+    pub fn moved(&mut self, id: WinId, region: &Region, screen: &Region) -> Result<()> {
+        let class = match self.placed.get_mut(&id) {
+            Some((_, r)) if r == region => return Ok(()),
+            Some((class, r)) => {
+                *r = *region;
+                class.clone()
+            }
+            None => return Ok(()),
+        };
+        self.update(&class, region, screen)
+    }
+
+    /// Remember that the window of `class` is at `region` on `screen`, writing the
+    /// state file if it moved.
+    fn update(&mut self, class: &str, region: &Region, screen: &Region) -> Result<()> {
+        let geometry = [
+            region.x.saturating_sub(screen.x),
+            region.y.saturating_sub(screen.y),
+            region.w,
+            region.h,
+        ];
+        if self.file.geometry.get(class) == Some(&geometry) {
+            return Ok(());
+        }
+        self.file.geometry.insert(class.to_string(), geometry);
+
+        let err = |e: String| PenroseError::Raw(e);
+        if let Some(dir) = self.state_file.parent() {
+            fs::create_dir_all(dir).map_err(|e| err(e.to_string()))?;
+        }
+        let s = toml::to_string(&self.file).map_err(|e| err(e.to_string()))?;
+        fs::write(&self.state_file, s).map_err(|e| err(e.to_string()))
+    }
+}
@@ -0,0 +1,226 @@
+//! Tests for the sizing of floating windows and the rules asking for it.
+use penrose::core::{
+    client::Client,
+    data_types::{Point, Region},
+};
+use proptest::prelude::*;
+
+use crate::{
+    floating::{center_on, float_region, FloatSize, Geometries, SizeHints},
+    preview::fake_clients,
+    rules::{parse_rules, set_floating},
+};
+
+fn screen() -> impl Strategy<Value = Region> {
+    (0u32..2000, 0u32..2000, 1u32..4000, 1u32..3000)
+        .prop_map(|(x, y, w, h)| Region::new(x, y, w, h))
+}
+
+fn pair() -> impl Strategy<Value = (u32, u32)> {
+    (1u32..5000, 1u32..5000)
+}
+
+fn float_size() -> impl Strategy<Value = FloatSize> {
+    (
+        prop_oneof![
+            (0.0f64..=1.0).prop_map(FloatSize::scale),
+            pair().prop_map(|(w, h)| FloatSize::absolute(w, h)),
+        ],
+        any::<bool>(),
+        proptest::option::of(pair()),
+        proptest::option::of(pair()),
+        any::<bool>(),
+    )
+        .prop_map(|(size, hints, min, max, keep_aspect)| FloatSize {
+            hints,
+            min,
+            max,
+            keep_aspect,
+            ..size
+        })
+}
+
+fn size_hints() -> impl Strategy<Value = SizeHints> {
+    (
+        proptest::option::of(pair()),
+        proptest::option::of(pair()),
+        proptest::option::of(pair()),
+        proptest::option::of((0.1f64..10.0, 0.0f64..5.0)),
+    )
+        .prop_map(|(size, min, max, aspect)| SizeHints {
+            size,
+            min,
+            max,
+            aspect: aspect.map(|(min, extra)| (min, min + extra)),
+        })
+}
+
+#[test]
+fn size_hints_are_parsed() {
+    // PMinSize | PMaxSize | PAspect, with a 16:9 to 2:1 aspect
+    let mut data = [0u32; 18];
+    data[0] = (1 << 4) | (1 << 5) | (1 << 7);
+    data[5..15].copy_from_slice(&[200, 100, 1600, 900, 0, 0, 16, 9, 2, 1]);
+
+    let hints = SizeHints::parse(&data);
+    assert_eq!(hints.size, None);
+    assert_eq!(hints.min, Some((200, 100)));
+    assert_eq!(hints.max, Some((1600, 900)));
+    assert_eq!(hints.aspect, Some((16.0 / 9.0, 2.0)));
+    assert_eq!(SizeHints::parse(&data[..4]), SizeHints::default());
+}
+
+#[test]
+fn sizes_in_rules_are_parsed() {
+    let rules = parse_rules(
+        r#"
+        [[rule]]
+        instance = "gcr-prompter"
+        size = [480, 240]
+        size_hints = true
+        max_size = [800, 400]
+
+        [[rule]]
+        window_type = "dialog"
+        placement = "parent"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(rules.len(), 2);
+
+    assert!(parse_rules("[[rule]]\nkeep_aspect = true").is_err());
+    assert!(parse_rules("[[rule]]\ntitle = \"(\"").is_err());
+    assert!(parse_rules("[[rule]]\nplacement = \"mouse\"").is_err());
+}
+
+#[test]
+fn geometries_are_restored_relative_to_the_screen() {
+    let path = std::env::temp_dir().join(format!("penrose-geometries-{}.toml", std::process::id()));
+    let left = Region::new(0, 0, 1920, 1080);
+    let right = Region::new(1920, 0, 1280, 720);
+
+    let mut saved = Geometries::new(&path);
+    saved.place(7, "Pavucontrol", Region::new(2240, 160, 640, 400));
+    saved
+        .moved(7, &Region::new(2020, 50, 600, 400), &right)
+        .unwrap();
+    let mut restored = Geometries::new(&path);
+    restored.load().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        restored.get("Pavucontrol", &left),
+        Some(Region::new(100, 50, 600, 400))
+    );
+    // moved back inside a screen too small for it
+    assert_eq!(
+        restored.get("Pavucontrol", &Region::new(0, 0, 500, 420)),
+        Some(Region::new(0, 20, 500, 400))
+    );
+    assert_eq!(restored.get("Gimp", &left), None);
+}
+
+#[test]
+fn clients_are_floated_through_their_serialized_fields() {
+    // the fields themselves are checked along with fake_clients
+    let fields = |c: &Client| toml::Value::try_from(c).unwrap();
+    let mut c = fake_clients(1).unwrap().remove(0);
+    let before = fields(&c);
+
+    set_floating(&mut c, true).unwrap();
+    let mut expected = before.clone();
+    expected
+        .as_table_mut()
+        .unwrap()
+        .insert("floating".into(), toml::Value::Boolean(true));
+    assert_eq!(fields(&c), expected);
+
+    set_floating(&mut c, false).unwrap();
+    assert_eq!(fields(&c), before);
+}
+
+proptest! {
+    #[test]
+    fn floating_windows_are_centered_inside_the_screen(
+        size in float_size(),
+        hints in size_hints(),
+        current in proptest::option::of(pair()),
+        screen in screen(),
+    ) {
+        let r = float_region(&size, &hints, current, &screen);
+        prop_assert!(r.x >= screen.x && r.x + r.w <= screen.x + screen.w);
+        prop_assert!(r.y >= screen.y && r.y + r.h <= screen.y + screen.h);
+        prop_assert!((r.x - screen.x).abs_diff(screen.x + screen.w - r.x - r.w) <= 1);
+        prop_assert!((r.y - screen.y).abs_diff(screen.y + screen.h - r.y - r.h) <= 1);
+    }
+
+    #[test]
+    fn floating_windows_respect_their_bounds(
+        size in float_size(),
+        hints in size_hints(),
+        screen in screen(),
+    ) {
+        let r = float_region(&size, &hints, None, &screen);
+        let max = |a: Option<(u32, u32)>, b: Option<(u32, u32)>| {
+            let (aw, ah) = a.unwrap_or((u32::MAX, u32::MAX));
+            let (bw, bh) = b.unwrap_or((u32::MAX, u32::MAX));
+            (aw.min(bw), ah.min(bh))
+        };
+        let (max_w, max_h) = max(size.max, hints.max);
+        prop_assert!(r.w <= max_w && r.h <= max_h);
+
+        let (min_w, min_h) = size.min.unwrap_or((1, 1));
+        let (hint_w, hint_h) = hints.min.unwrap_or((1, 1));
+        if min_w.max(hint_w) <= max_w.min(screen.w) {
+            prop_assert!(r.w >= min_w.max(hint_w));
+        }
+        if min_h.max(hint_h) <= max_h.min(screen.h) {
+            prop_assert!(r.h >= min_h.max(hint_h));
+        }
+    }
+
+    #[test]
+    fn floating_windows_are_centered_on_points_inside_the_screen(
+        (w, h) in pair(),
+        screen in screen(),
+        (x, y) in (0u32..6000, 0u32..5000),
+    ) {
+        let r = center_on(Region::new(0, 0, w, h), Point::new(x, y), &screen);
+        prop_assert!(r.x >= screen.x && r.x + r.w <= screen.x + screen.w);
+        prop_assert!(r.y >= screen.y && r.y + r.h <= screen.y + screen.h);
+        prop_assert_eq!((r.w, r.h), (w.min(screen.w), h.min(screen.h)));
+
+        let fits = |lo: u32, size: u32, at: u32, len: u32| at >= lo + size / 2 && at + size - size / 2 <= lo + len;
+        if fits(screen.x, r.w, x, screen.w) {
+            prop_assert_eq!(r.x + r.w / 2, x);
+        }
+        if fits(screen.y, r.h, y, screen.h) {
+            prop_assert_eq!(r.y + r.h / 2, y);
+        }
+    }
+}
+
+#[test]
+fn geometries_are_only_kept_for_windows_the_user_moved() {
+    let path = std::env::temp_dir().join(format!("penrose-unmoved-{}.toml", std::process::id()));
+    let screen = Region::new(0, 0, 1920, 1080);
+    let placed = Region::new(640, 270, 640, 540);
+
+    let mut geometries = Geometries::new(&path);
+    geometries.place(7, "Pavucontrol", placed);
+    // raising a window or laying out its workspace leaves it where it is
+    geometries.moved(7, &placed, &screen).unwrap();
+    // windows that weren't placed are never followed
+    geometries
+        .moved(8, &Region::new(0, 0, 100, 100), &screen)
+        .unwrap();
+    assert_eq!(geometries.get("Pavucontrol", &screen), None);
+    assert!(!path.exists());
+
+    geometries.forget(7);
+    geometries
+        .moved(7, &Region::new(0, 0, 100, 100), &screen)
+        .unwrap();
+    assert_eq!(geometries.get("Pavucontrol", &screen), None);
+    assert!(!path.exists());
+}
@@ -0,0 +1,155 @@
+use penrose::core::{
+    client::Client,
+    data_types::{Change, Region, ResizeAction, WinId},
+    layout::LayoutFunc,
+};
+use std::cell::Cell;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gaps {
+    /// Gap between the clients and the edges of the screen
+    pub outer: u32,
+    /// Gap between neighbouring clients
+    pub inner: u32,
+    /// Drop all gaps when a single client is visible
+    pub smart: bool,
+    /// Lay out without any gaps while false, keeping their sizes for when they come back
+    pub enabled: bool,
+}
+
+thread_local! {
+    static GAPS: Cell<Gaps> = const {
+        Cell::new(Gaps {
+            outer: 0,
+            inner: 0,
+            smart: true,
+            enabled: true,
+        })
+    };
+}
+
+/// Replace the gaps, turning them back on if they were toggled off.
+pub fn set_gaps(outer: u32, inner: u32, smart: bool) {
+    GAPS.with(|g| {
+        g.set(Gaps {
+            outer,
+            inner,
+            smart,
+            enabled: true,
+        })
+    });
+}
+
+/// The gaps the gapped layouts are currently using.
+pub fn gaps() -> Gaps {
+    GAPS.with(|g| g.get())
+}
+
+/// Grow or shrink both the outer and inner gaps by `step` pixels.
+pub fn change_gaps(change: Change, step: u32) -> Gaps {
+    GAPS.with(|g| {
+        let mut gaps = g.get();
+        match change {
+            Change::More => {
+                gaps.outer += step;
+                gaps.inner += step;
+            }
+            Change::Less => {
+                gaps.outer = gaps.outer.saturating_sub(step);
+                gaps.inner = gaps.inner.saturating_sub(step);
+            }
+        }
+        g.set(gaps);
+        gaps
+    })
+}
+
+/// Turn all gaps off or back on, returning whether they are on now.
+pub fn toggle_gaps() -> bool {
+    GAPS.with(|g| {
+        let mut gaps = g.get();
+        gaps.enabled = !gaps.enabled;
+        g.set(gaps);
+        gaps.enabled
+    })
+}
+
+/// Turn smart gaps off or back on, returning whether they are on now.
+pub fn toggle_smart_gaps() -> bool {
+    GAPS.with(|g| {
+        let mut gaps = g.get();
+        gaps.smart = !gaps.smart;
+        g.set(gaps);
+        gaps.smart
+    })
+}
+
+/// Shrink `region` by the given amount of pixels on each side, keeping at least one pixel.
+fn shrink(region: &Region, left: u32, top: u32, right: u32, bottom: u32) -> Region {
+    let w = region.w.saturating_sub(left + right).max(region.w.min(1));
+    let h = region.h.saturating_sub(top + bottom).max(region.h.min(1));
+    let left = left.min(region.w.saturating_sub(w));
+    let top = top.min(region.h.saturating_sub(h));
+    Region::new(region.x + left, region.y + top, w, h)
+}
+
+/// Leave half of the inner gap on every side of `region` that doesn't touch the edges of
+/// `area`, so neighbours end up a full inner gap apart.
+fn inner_gaps(region: &Region, area: &Region, inner: u32) -> Region {
+    let (low, high) = (inner / 2, inner - inner / 2);
+    let gap = |at_edge: bool, px: u32| if at_edge { 0 } else { px };
+    shrink(
+        region,
+        gap(region.x <= area.x, high),
+        gap(region.y <= area.y, high),
+        gap(region.x + region.w >= area.x + area.w, low),
+        gap(region.y + region.h >= area.y + area.h, low),
+    )
+}
+
+/**
+ * Run `layout` with the current gaps: the screen is shrunk by the outer gap before
+ * arranging and the resulting regions are pulled apart by the inner gap. With smart gaps
+ * a lone visible client gets the whole screen.
+ */
+pub fn with_gaps(
+    layout: LayoutFunc,
+    clients: &[&Client],
+    focused: Option<WinId>,
+    monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    let gaps = gaps();
+    if !gaps.enabled || (gaps.outer == 0 && gaps.inner == 0) {
+        return layout(clients, focused, monitor_region, max_main, ratio);
+    }
+
+    let o = gaps.outer;
+    let area = shrink(monitor_region, o, o, o, o);
+    let actions = layout(clients, focused, &area, max_main, ratio);
+    let visible = actions.iter().filter(|(_, r)| r.is_some()).count();
+    if gaps.smart && visible <= 1 {
+        return layout(clients, focused, monitor_region, max_main, ratio);
+    }
+
+    actions
+        .into_iter()
+        .map(|(id, r)| (id, r.map(|r| inner_gaps(&r, &area, gaps.inner))))
+        .collect()
+}
+
+/// Wrap a [LayoutFunc] so that it is run through [with_gaps]. The macro can't see the
+/// [LayoutConf][penrose::core::layout::LayoutConf] of the layout, so keeping `gapless`
+/// layouts edge to edge is up to the caller not wrapping them, as `main` does.
+macro_rules! gapped {
+    ($layout:expr) => {
+        |clients: &[&penrose::core::client::Client],
+         focused: Option<penrose::core::data_types::WinId>,
+         region: &penrose::core::data_types::Region,
+         max_main: u32,
+         ratio: f32| {
+            $crate::gaps::with_gaps($layout, clients, focused, region, max_main, ratio)
+        }
+    };
+}
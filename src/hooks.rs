@@ -1,60 +1,41 @@
 use penrose::{
-    core::{
-        client::Client,
-        hooks::Hook,
-        manager::WindowManager,
-        helpers::spawn,
-        data_types::WinId,
-        xconnection::XConn,
-    },
+    core::{data_types::WinId, hooks::Hook, manager::WindowManager, xconnection::XConn},
     Result,
 };
 
-pub struct CenterFloat {
-    class_names: Vec<String>,
-    scale: f64,
-}
+use crate::{bsp::bsp_focus, layouts::reset_weight};
 
-impl CenterFloat {
-    pub fn new(class_names: Vec<impl Into<String>>, scale: f64) -> Box<Self> {
-        Box::new(Self {
-            class_names: class_names.into_iter().map(|c| c.into()).collect(),
-            scale,
-        })
-    }
+/// Drops the layout weight of clients that go away, so that a new window reusing the same
+/// id starts from the default size.
+pub struct ForgetWeights {}
 
-    fn centered_above<X: XConn>(&self, id: WinId, wm: &mut WindowManager<X>) -> Result<()> {
-        if let Some(region) = wm.screen_size(wm.active_screen_index()) {
-            let r = region.scale_w(self.scale).scale_h(self.scale);
-            wm.position_client(id, r.centered_in(&region)?, true)?;
-        }
-        wm.show_client(id)
+impl ForgetWeights {
+    pub fn new() -> Self {
+        Self {}
     }
 }
 
-impl<X: XConn> Hook<X> for CenterFloat {
-    fn new_client(&mut self, wm: &mut WindowManager<X>, c: &mut Client) -> Result<()> {
-        if self.class_names.contains(&c.wm_class().to_string()) {
-            self.centered_above(c.id(), wm)?;
-        }
-
+impl<X: XConn> Hook<X> for ForgetWeights {
+    fn remove_client(&mut self, _: &mut WindowManager<X>, id: WinId) -> Result<()> {
+        reset_weight(id);
         Ok(())
     }
 }
 
-pub struct StartupScript {
-}
+/// Keeps the split trees of [bsp][crate::bsp::bsp] and
+/// [focus_dwindle][crate::bsp::focus_dwindle] on the client that has the focus now, not
+/// the one that had it when they were last laid out.
+pub struct BspFocus {}
 
-impl StartupScript {
+impl BspFocus {
     pub fn new() -> Self {
         Self {}
     }
 }
 
-impl<X: XConn> Hook<X> for StartupScript {
-    fn startup(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
-        let _ = wm.set_root_window_name("root");
-        let _ = spawn("lbarstat");
+impl<X: XConn> Hook<X> for BspFocus {
+    fn focus_change(&mut self, _: &mut WindowManager<X>, id: WinId) -> Result<()> {
+        bsp_focus(id);
         Ok(())
     }
 }
@@ -0,0 +1,590 @@
+//! Property tests for the layouts: whatever the clients and the screen, a layout has to
+//! tile the screen without overlapping clients and without panicking.
+use penrose::core::{
+    client::Client,
+    data_types::{Change, Region, ResizeAction, WinId},
+    layout::{monocle, Layout, LayoutConf, LayoutFunc},
+};
+use proptest::prelude::*;
+
+use crate::{
+    bsp::{bsp, bsp_focus, bsp_rotate, focus_dwindle, Split},
+    declarative::{arrange_definition, parse_layouts, register_layouts},
+    gaps::{set_gaps, with_gaps},
+    layouts::{
+        centered_main, change_weight, columns, deck, dwindle, golden, grid, reset_weight,
+        set_deck_sliver, set_dwindle_overflow, set_spiral, spiral, tabbed, take_tab_strip,
+        three_column, three_column_mid, toggle_centered_main_always, Corner, Overflow, Rotation,
+    },
+    preview::{fake_clients, Preview},
+    transform::{toggle_flip, toggle_mirror, toggle_transpose, with_transform},
+};
+
+const LAYOUTS: &[(&str, LayoutFunc)] = &[
+    ("dwindle", dwindle),
+    ("spiral", spiral),
+    ("grid", grid),
+    ("centered_main", centered_main),
+    ("three_column", three_column),
+    ("three_column_mid", three_column_mid),
+    ("columns", columns),
+    ("bsp", bsp),
+    ("focus_dwindle", focus_dwindle),
+    ("golden", golden),
+    ("deck", deck),
+];
+
+const DECLARED: &str = r#"
+[[layout]]
+symbol = "[tall]"
+ratio = 0.6
+
+[layout.root]
+split = "columns"
+sizes = ["ratio", "inverse_ratio"]
+children = [
+    { slot = "max_main" },
+    { split = "rows", sizes = [2, 1], children = [{ slot = 1 }, { slot = "rest", arrange = "grid" }] },
+]
+
+[[layout]]
+symbol = "[quad]"
+overflow = "hide"
+
+[layout.root]
+split = "rows"
+children = [
+    { split = "columns", children = [{ slot = 1 }, { slot = 1, arrange = "stack" }] },
+    { split = "columns", children = [{ slot = 1 }, { slot = "max_main", arrange = "columns" }] },
+]
+"#;
+
+fn area(r: &Region) -> u64 {
+    r.w as u64 * r.h as u64
+}
+
+fn overlap(a: &Region, b: &Region) -> bool {
+    a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h
+}
+
+fn inside(r: &Region, screen: &Region) -> bool {
+    r.x >= screen.x
+        && r.y >= screen.y
+        && r.x + r.w <= screen.x + screen.w
+        && r.y + r.h <= screen.y + screen.h
+}
+
+/// Every client gets exactly one action and the visible regions stay on the screen without
+/// overlapping. Clients stacked on the very same region count as one.
+fn check_actions(
+    actions: &[ResizeAction],
+    ids: &[WinId],
+    screen: &Region,
+) -> Result<Vec<Region>, TestCaseError> {
+    let mut got: Vec<WinId> = actions.iter().map(|(id, _)| *id).collect();
+    got.sort_unstable();
+    prop_assert_eq!(&got, &ids.to_vec(), "every client needs exactly one action");
+
+    let mut regions: Vec<Region> = actions.iter().filter_map(|(_, r)| *r).collect();
+    regions.sort_by_key(|r| r.values());
+    regions.dedup();
+    for (i, r) in regions.iter().enumerate() {
+        prop_assert!(inside(r, screen), "{:?} is not inside {:?}", r, screen);
+        for other in &regions[i + 1..] {
+            prop_assert!(!overlap(r, other), "{:?} overlaps {:?}", r, other);
+        }
+    }
+    Ok(regions)
+}
+
+/// [check_actions] and the visible regions cover the whole screen.
+fn check_tiling(
+    actions: &[ResizeAction],
+    ids: &[WinId],
+    screen: &Region,
+) -> Result<(), TestCaseError> {
+    let regions = check_actions(actions, ids, screen)?;
+    if !ids.is_empty() {
+        let covered: u64 = regions.iter().map(area).sum();
+        prop_assert_eq!(covered, area(screen), "the screen is not fully covered");
+    }
+    Ok(())
+}
+
+fn tiles_the_screen(
+    layout: LayoutFunc,
+    n: usize,
+    screen: Region,
+    max_main: u32,
+    ratio: f32,
+) -> Result<(), TestCaseError> {
+    let clients = fake_clients(n).unwrap();
+    let refs: Vec<&Client> = clients.iter().collect();
+    let ids: Vec<WinId> = clients.iter().map(|c| c.id()).collect();
+    let focused = ids.first().copied();
+
+    let actions = layout(&refs, focused, &screen, max_main, ratio);
+    check_tiling(&actions, &ids, &screen)
+}
+
+fn screen() -> impl Strategy<Value = Region> {
+    (0u32..2000, 0u32..2000, 0u32..4000, 0u32..3000)
+        .prop_map(|(x, y, w, h)| Region::new(x, y, w, h))
+}
+
+fn overflow() -> impl Strategy<Value = Overflow> {
+    prop_oneof![
+        Just(Overflow::Hide),
+        Just(Overflow::Stack),
+        Just(Overflow::Shrink),
+        Just(Overflow::Grid),
+    ]
+}
+
+fn corner() -> impl Strategy<Value = Corner> {
+    prop_oneof![
+        Just(Corner::TopLeft),
+        Just(Corner::TopRight),
+        Just(Corner::BottomRight),
+        Just(Corner::BottomLeft),
+    ]
+}
+
+fn rotation() -> impl Strategy<Value = Rotation> {
+    prop_oneof![Just(Rotation::Clockwise), Just(Rotation::CounterClockwise)]
+}
+
+#[test]
+fn previews_are_drawn() {
+    let preview = |focus: &str| {
+        let args = "dwdl --clients 4 --size 800x400 --columns 32 --focus ".to_string() + focus;
+        Preview::from_args(args.split(' ').map(String::from)).unwrap()
+    };
+    let layouts = || {
+        vec![
+            Layout::new("[mono]", LayoutConf::default(), monocle, 1, 0.6),
+            Layout::new("[dwdl]", LayoutConf::default(), dwindle, 1, 0.6),
+        ]
+    };
+    let expected = "\
+[dwdl] with 4 clients on 800x400
++-----------------++-----------+
+|1                ||2*         |
+|                 ||           |
+|                 |+-----------+
+|                 |+-----++----+
+|                 ||3    ||4   |
+|                 ||     ||    |
++-----------------++-----++----+
+";
+    assert_eq!(preview("2").render(layouts()).unwrap(), expected);
+    assert_eq!(
+        preview("4").render(layouts()).unwrap(),
+        expected.replace("|2*", "|2 ").replace("|4 ", "|4*")
+    );
+    assert!(!preview("none").render(layouts()).unwrap().contains('*'));
+    assert!(Preview::from_args("dwdl --focus 0".split(' ').map(String::from)).is_err());
+}
+
+#[test]
+fn fake_clients_serialize_back_to_their_fields() {
+    let clients = fake_clients(2).unwrap();
+    let ids: Vec<WinId> = clients.iter().map(|c| c.id()).collect();
+    assert_eq!(ids, vec![1, 2]);
+    assert_eq!(clients[1].wm_name(), "client 2");
+
+    // nothing penrose expects is missing and nothing given is dropped
+    let fields = toml::Value::try_from(&clients[0]).unwrap();
+    let mut keys: Vec<&String> = fields.as_table().unwrap().keys().collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        [
+            "floating",
+            "fullscreen",
+            "id",
+            "mapped",
+            "wm_class",
+            "wm_managed",
+            "wm_name",
+            "workspace"
+        ]
+    );
+    assert_eq!(fields["workspace"].as_integer(), Some(0));
+    assert_eq!(fields["mapped"].as_bool(), Some(true));
+}
+
+/// The region each client of `ids` got.
+fn regions(actions: &[ResizeAction], ids: &[WinId]) -> Vec<Option<Region>> {
+    ids.iter()
+        .map(|id| actions.iter().find(|(c, _)| c == id).and_then(|(_, r)| *r))
+        .collect()
+}
+
+#[test]
+fn bsp_splits_the_client_focused_after_the_last_layout() {
+    let screen = Region::new(0, 0, 1000, 1000);
+    let clients = fake_clients(3).unwrap();
+    let refs: Vec<&Client> = clients.iter().collect();
+
+    bsp(&refs[..2], Some(2), &screen, 1, 0.5);
+    bsp_focus(1);
+    // by the time the layout runs the new client has the focus
+    let actions = bsp(&refs, Some(3), &screen, 1, 0.5);
+    assert_eq!(
+        regions(&actions, &[1, 2, 3]),
+        vec![
+            Some(Region::new(0, 0, 500, 500)),
+            Some(Region::new(500, 0, 500, 1000)),
+            Some(Region::new(0, 500, 500, 500)),
+        ]
+    );
+}
+
+#[test]
+fn focus_dwindle_splits_the_client_focused_after_the_last_layout() {
+    let screen = Region::new(0, 0, 1000, 1000);
+    let clients = fake_clients(4).unwrap();
+    let refs: Vec<&Client> = clients.iter().collect();
+
+    focus_dwindle(&refs[..3], Some(3), &screen, 1, 0.5);
+    bsp_focus(1);
+    let actions = focus_dwindle(&refs, Some(4), &screen, 1, 0.5);
+    assert_eq!(
+        regions(&actions, &[1, 2, 3, 4]),
+        vec![
+            Some(Region::new(0, 0, 500, 500)),
+            Some(Region::new(500, 0, 500, 500)),
+            Some(Region::new(500, 500, 500, 500)),
+            Some(Region::new(0, 500, 500, 500)),
+        ]
+    );
+}
+
+#[test]
+fn tree_commands_only_change_the_tree_they_are_given() {
+    let screen = Region::new(0, 0, 1000, 1000);
+    let clients = fake_clients(2).unwrap();
+    let refs: Vec<&Client> = clients.iter().collect();
+    let side_by_side = vec![
+        Some(Region::new(0, 0, 500, 1000)),
+        Some(Region::new(500, 0, 500, 1000)),
+    ];
+    bsp(&refs, Some(1), &screen, 1, 0.5);
+    focus_dwindle(&refs, Some(1), &screen, 1, 0.5);
+
+    // the clients aren't on the second workspace
+    bsp_rotate((1, Split::Longest), 2);
+    bsp_rotate((0, Split::Alternate), 2);
+    assert_eq!(
+        regions(&bsp(&refs, Some(1), &screen, 1, 0.5), &[1, 2]),
+        side_by_side
+    );
+    assert_eq!(
+        regions(&focus_dwindle(&refs, Some(1), &screen, 1, 0.5), &[1, 2]),
+        vec![
+            Some(Region::new(0, 0, 1000, 500)),
+            Some(Region::new(0, 500, 1000, 500)),
+        ]
+    );
+}
+
+#[test]
+fn declared_layouts_are_parsed() {
+    let defs = parse_layouts(DECLARED).unwrap();
+    assert_eq!(defs.len(), 2);
+    assert_eq!(defs[0].symbol, "[tall]");
+    assert_eq!(defs[0].max_main, 1);
+    assert_eq!(defs[1].ratio, 0.6);
+    assert_eq!(register_layouts(defs).unwrap().len(), 2);
+}
+
+#[test]
+fn declared_layouts_that_do_not_fit_are_named() {
+    let declared: String = (0..10)
+        .map(|i| format!("[[layout]]\nsymbol = \"[l{}]\"\nroot = {{ slot = 1 }}\n", i))
+        .collect();
+    let err = register_layouts(parse_layouts(&declared).unwrap())
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("[l8], [l9]"), "{}", err);
+    assert!(!err.contains("[l7]"), "{}", err);
+}
+
+#[test]
+fn invalid_declared_layouts_are_rejected() {
+    let invalid = [
+        "[[layout]]\nsymbol = \"[bad]\"\nroot = { split = \"rows\", children = [] }",
+        "[[layout]]\nsymbol = \"[bad]\"\nroot = { split = \"rows\", sizes = [1], children = [{ slot = 1 }, { slot = 1 }] }",
+        "[[layout]]\nsymbol = \"[bad]\"\nroot = { split = \"rows\", sizes = [0, 1], children = [{ slot = 1 }, { slot = 1 }] }",
+        "[[layout]]\nsymbol = \"[bad]\"\nroot = { slot = \"some\" }",
+        "[[layout]]\nsymbol = \"[bad]\"\nmain = 2\nroot = { slot = 1 }",
+    ];
+    for s in invalid.iter() {
+        assert!(parse_layouts(s).is_err(), "{} should be rejected", s);
+    }
+}
+
+proptest! {
+    #[test]
+    fn dwindle_tiles_the_screen(
+        n in 0usize..40,
+        screen in screen(),
+        max_main in 0u32..5,
+        ratio in 0.0f32..=1.0,
+        overflow in overflow(),
+        min_size in 1u32..300,
+    ) {
+        set_dwindle_overflow(overflow, min_size);
+        tiles_the_screen(dwindle, n, screen, max_main, ratio)?;
+    }
+
+    #[test]
+    fn spiral_tiles_the_screen(
+        n in 0usize..40,
+        screen in screen(),
+        max_main in 0u32..5,
+        ratio in 0.0f32..=1.0,
+        overflow in overflow(),
+        corner in corner(),
+        rotation in rotation(),
+    ) {
+        set_dwindle_overflow(overflow, 50);
+        set_spiral(corner, rotation);
+        tiles_the_screen(spiral, n, screen, max_main, ratio)?;
+    }
+
+    #[test]
+    fn grid_tiles_the_screen(
+        n in 0usize..60,
+        screen in screen(),
+        max_main in 0u32..5,
+        ratio in 0.0f32..=1.0,
+    ) {
+        tiles_the_screen(grid, n, screen, max_main, ratio)?;
+    }
+
+    #[test]
+    fn centered_main_tiles_the_screen(
+        n in 0usize..40,
+        screen in screen(),
+        max_main in 0u32..5,
+        ratio in 0.0f32..=1.0,
+        always in any::<bool>(),
+    ) {
+        let clients = fake_clients(n).unwrap();
+        let refs: Vec<&Client> = clients.iter().collect();
+        let ids: Vec<WinId> = clients.iter().map(|c| c.id()).collect();
+
+        if always {
+            toggle_centered_main_always();
+        }
+        let actions = centered_main(&refs, None, &screen, max_main, ratio);
+        if always {
+            toggle_centered_main_always();
+        }
+
+        // Keeping the main column centered for a single stack client leaves the left empty
+        if always && max_main > 0 && n == max_main as usize + 1 {
+            check_actions(&actions, &ids, &screen)?;
+        } else {
+            check_tiling(&actions, &ids, &screen)?;
+        }
+    }
+
+    #[test]
+    fn three_column_tiles_the_screen(
+        n in 0usize..40,
+        screen in screen(),
+        max_main in 0u32..5,
+        ratio in 0.0f32..=1.0,
+    ) {
+        tiles_the_screen(three_column, n, screen, max_main, ratio)?;
+        tiles_the_screen(three_column_mid, n, screen, max_main, ratio)?;
+    }
+
+    #[test]
+    fn columns_tiles_the_screen(
+        n in 0usize..40,
+        screen in screen(),
+        max_main in 0u32..8,
+    ) {
+        tiles_the_screen(columns, n, screen, max_main, 0.5)?;
+    }
+
+    #[test]
+    fn bsp_tiles_the_screen(
+        counts in prop::collection::vec(0usize..20, 1..6),
+        screen in screen(),
+    ) {
+        // The tree of a workspace outlives a single arrangement, so grow and shrink it
+        for n in counts {
+            tiles_the_screen(bsp, n, screen, 1, 0.5)?;
+            tiles_the_screen(focus_dwindle, n, screen, 1, 0.5)?;
+        }
+    }
+
+    #[test]
+    fn weighted_layouts_tile_the_screen(
+        layout in 0..LAYOUTS.len(),
+        steps in prop::collection::vec(-4i32..16, 0..30),
+        screen in screen(),
+        max_main in 0u32..5,
+        ratio in 0.0f32..=1.0,
+    ) {
+        let (_, layout) = LAYOUTS[layout];
+        let n = steps.len();
+        for (id, step) in steps.iter().enumerate() {
+            let change = if *step < 0 { Change::Less } else { Change::More };
+            change_weight(id as WinId, change, step.abs() as f32 * 0.25);
+        }
+        let result = tiles_the_screen(layout, n, screen, max_main, ratio);
+        (0..n).for_each(|id| reset_weight(id as WinId));
+        result?;
+    }
+
+    #[test]
+    fn declared_layouts_tile_the_screen(
+        n in 0usize..30,
+        screen in screen(),
+        max_main in 0u32..5,
+        ratio in 0.0f32..=1.0,
+    ) {
+        register_layouts(parse_layouts(DECLARED).unwrap()).unwrap();
+        let clients = fake_clients(n).unwrap();
+        let refs: Vec<&Client> = clients.iter().collect();
+        let ids: Vec<WinId> = clients.iter().map(|c| c.id()).collect();
+
+        for index in 0..2 {
+            let actions = arrange_definition(index, &refs, &screen, max_main, ratio);
+            check_tiling(&actions, &ids, &screen)?;
+        }
+    }
+
+    #[test]
+    fn golden_enlarges_the_focused_row_and_column(
+        n in 2usize..30,
+        screen in (0u32..2000, 0u32..2000, 100u32..4000, 100u32..3000)
+            .prop_map(|(x, y, w, h)| Region::new(x, y, w, h)),
+        focused in 0usize..30,
+        ratio in 0.5f32..0.9,
+    ) {
+        let clients = fake_clients(n).unwrap();
+        let refs: Vec<&Client> = clients.iter().collect();
+        let ids: Vec<WinId> = clients.iter().map(|c| c.id()).collect();
+        let focused = ids[focused % n];
+
+        let actions = golden(&refs, Some(focused), &screen, 1, ratio);
+        check_tiling(&actions, &ids, &screen)?;
+        let region = |id: WinId| actions.iter().find(|(c, _)| *c == id).and_then(|(_, r)| *r).unwrap();
+        let f = region(focused);
+        for id in ids.iter().filter(|id| **id != focused) {
+            let r = region(*id);
+            prop_assert!(r.h <= f.h + 1, "{} is taller than the focused client", id);
+            if r.y == f.y {
+                prop_assert!(r.w <= f.w + 1, "{} is wider than the focused client", id);
+            }
+        }
+    }
+
+    #[test]
+    fn deck_opens_the_focused_stack_client(
+        n in 2usize..20,
+        screen in (0u32..2000, 0u32..2000, 100u32..4000, 500u32..3000)
+            .prop_map(|(x, y, w, h)| Region::new(x, y, w, h)),
+        max_main in 1u32..3,
+        focused in 0usize..20,
+    ) {
+        let clients = fake_clients(n).unwrap();
+        let refs: Vec<&Client> = clients.iter().collect();
+        let ids: Vec<WinId> = clients.iter().map(|c| c.id()).collect();
+        let stack = &ids[(max_main as usize).min(n - 1)..];
+        let open = stack[focused % stack.len()];
+
+        set_deck_sliver(20);
+        let actions = deck(&refs, Some(open), &screen, max_main, 0.6);
+        check_tiling(&actions, &ids, &screen)?;
+        // focusing a main client leaves the stack as it was
+        prop_assert_eq!(&deck(&refs, Some(ids[0]), &screen, max_main, 0.6), &actions);
+
+        if n > max_main as usize {
+            let region = |id: WinId| actions.iter().find(|(c, _)| *c == id).and_then(|(_, r)| *r).unwrap();
+            for id in stack.iter().filter(|id| **id != open) {
+                prop_assert!(region(*id).h <= 20, "{} is not a sliver", id);
+                prop_assert!(region(*id).h < region(open).h);
+            }
+        }
+    }
+
+    #[test]
+    fn tabbed_shows_the_focused_client_under_the_strip(
+        n in 1usize..20,
+        screen in screen(),
+        focused in 0u32..25,
+    ) {
+        let clients = fake_clients(n).unwrap();
+        let refs: Vec<&Client> = clients.iter().collect();
+        let ids: Vec<WinId> = clients.iter().map(|c| c.id()).collect();
+
+        let actions = tabbed(&refs, Some(focused), &screen, 1, 0.5);
+        let regions = check_actions(&actions, &ids, &screen)?;
+        prop_assert_eq!(regions.len(), 1);
+
+        let strip = take_tab_strip(0).expect("tabbed should leave a tab strip");
+        prop_assert_eq!(&strip.clients, &ids);
+        prop_assert!(!overlap(&strip.region, &regions[0]));
+        prop_assert_eq!(area(&strip.region) + area(&regions[0]), area(&screen));
+    }
+
+    #[test]
+    fn transformed_layouts_tile_the_screen(
+        layout in 0..LAYOUTS.len(),
+        n in 0usize..30,
+        screen in screen(),
+        max_main in 0u32..5,
+        ratio in 0.0f32..=1.0,
+        transform in any::<(bool, bool, bool)>(),
+    ) {
+        let (_, layout) = LAYOUTS[layout];
+        let (mirror, flip, transpose) = transform;
+        let toggle = || {
+            if mirror {
+                toggle_mirror(0);
+            }
+            if flip {
+                toggle_flip(0);
+            }
+            if transpose {
+                toggle_transpose(0);
+            }
+        };
+
+        toggle();
+        let clients = fake_clients(n).unwrap();
+        let refs: Vec<&Client> = clients.iter().collect();
+        let ids: Vec<WinId> = clients.iter().map(|c| c.id()).collect();
+        let actions = with_transform(layout, &refs, None, &screen, max_main, ratio);
+        toggle();
+        check_tiling(&actions, &ids, &screen)?;
+    }
+
+    #[test]
+    fn gapped_layouts_do_not_overlap(
+        layout in 0..LAYOUTS.len(),
+        n in 0usize..30,
+        screen in screen(),
+        max_main in 0u32..5,
+        ratio in 0.0f32..=1.0,
+        outer in 0u32..50,
+        inner in 0u32..50,
+        smart in any::<bool>(),
+    ) {
+        let (_, layout) = LAYOUTS[layout];
+        set_gaps(outer, inner, smart);
+        let clients = fake_clients(n).unwrap();
+        let refs: Vec<&Client> = clients.iter().collect();
+        let ids: Vec<WinId> = clients.iter().map(|c| c.id()).collect();
+        let actions = with_gaps(layout, &refs, None, &screen, max_main, ratio);
+        check_actions(&actions, &ids, &screen)?;
+    }
+}
@@ -1,17 +1,238 @@
 use penrose::core::{
     client::Client,
-    data_types::{Region, ResizeAction, WinId},
+    data_types::{Change, Region, ResizeAction, WinId},
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
 };
 
+/**
+ * What dwindle does with the clients that are left once a region becomes smaller than
+ * the minimum size.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Only the first remaining client is shown, the rest are unmapped
+    Hide,
+    /// The remaining clients are stacked monocle-style in the last region
+    Stack,
+    /// The minimum size is halved until every client fits
+    Shrink,
+    /// The remaining clients are tiled as a grid inside the last region
+    Grid,
+}
 
-fn dwindle_recurisive(
+impl Overflow {
+    /// The policy that follows this one when cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            Overflow::Hide => Overflow::Stack,
+            Overflow::Stack => Overflow::Shrink,
+            Overflow::Shrink => Overflow::Grid,
+            Overflow::Grid => Overflow::Hide,
+        }
+    }
+}
+
+thread_local! {
+    static DWINDLE_OVERFLOW: Cell<(Overflow, u32)> = const { Cell::new((Overflow::Stack, 50)) };
+    static HIDDEN_CLIENTS: RefCell<HashMap<usize, usize>> = RefCell::new(HashMap::new());
+}
+
+/// Set the overflow policy and minimum region size used by `dwindle`.
+pub fn set_dwindle_overflow(overflow: Overflow, min_size: u32) {
+    DWINDLE_OVERFLOW.with(|o| o.set((overflow, min_size)));
+}
+
+/// Switch `dwindle` to the next overflow policy, returning the new one.
+pub fn cycle_dwindle_overflow() -> Overflow {
+    DWINDLE_OVERFLOW.with(|o| {
+        let (overflow, min_size) = o.get();
+        o.set((overflow.next(), min_size));
+        overflow.next()
+    })
+}
+
+/// Number of clients that the last layout of `workspace` could not show.
+pub fn hidden_clients(workspace: usize) -> usize {
+    HIDDEN_CLIENTS.with(|h| h.borrow().get(&workspace).copied().unwrap_or(0))
+}
+
+/// Forget the hidden client count of `workspace`, e.g. when its layout changes.
+pub fn clear_hidden_clients(workspace: usize) {
+    HIDDEN_CLIENTS.with(|h| h.borrow_mut().remove(&workspace));
+}
+
+fn set_hidden_clients(clients: &[&Client], hidden: usize) {
+    if let Some(c) = clients.first() {
+        HIDDEN_CLIENTS.with(|h| h.borrow_mut().insert(c.workspace(), hidden));
+    }
+}
+
+/// Like `Region::as_rows` and `Region::as_columns`, but the pixels left over by the division
+/// go one each to the first pieces instead of being left uncovered at the end.
+pub(crate) trait EvenSplit {
+    fn rows(&self, n: u32) -> Vec<Region>;
+    fn columns(&self, n: u32) -> Vec<Region>;
+    /// Rows with heights in proportion to `weights`.
+    fn rows_weighted(&self, weights: &[f32]) -> Vec<Region>;
+    /// Columns with widths in proportion to `weights`.
+    fn columns_weighted(&self, weights: &[f32]) -> Vec<Region>;
+}
+
+/// Lengths of `n` pieces that add up to `length`.
+fn even_lengths(length: u32, n: u32) -> impl Iterator<Item = u32> {
+    (0..n).map(move |i| length / n + if i < length % n { 1 } else { 0 })
+}
+
+/// Lengths in proportion to `weights` that add up to `length`.
+fn weighted_lengths(length: u32, weights: &[f32]) -> Vec<u32> {
+    let total: f64 = weights.iter().map(|w| *w as f64).sum();
+    if total <= 0.0 {
+        return even_lengths(length, weights.len() as u32).collect();
+    }
+    let mut acc = 0.0;
+    let mut start = 0;
+    weights
+        .iter()
+        .map(|w| {
+            acc += *w as f64;
+            let end = ((length as f64 * acc / total).round() as u32).clamp(start, length);
+            let piece = end - start;
+            start = end;
+            piece
+        })
+        .collect()
+}
+
+impl EvenSplit for Region {
+    fn rows(&self, n: u32) -> Vec<Region> {
+        if n <= 1 {
+            return vec![*self];
+        }
+        let mut y = self.y;
+        even_lengths(self.h, n)
+            .map(|h| {
+                y += h;
+                Region::new(self.x, y - h, self.w, h)
+            })
+            .collect()
+    }
+
+    fn columns(&self, n: u32) -> Vec<Region> {
+        if n <= 1 {
+            return vec![*self];
+        }
+        let mut x = self.x;
+        even_lengths(self.w, n)
+            .map(|w| {
+                x += w;
+                Region::new(x - w, self.y, w, self.h)
+            })
+            .collect()
+    }
+
+    fn rows_weighted(&self, weights: &[f32]) -> Vec<Region> {
+        if weights.len() <= 1 {
+            return vec![*self];
+        }
+        let mut y = self.y;
+        weighted_lengths(self.h, weights)
+            .into_iter()
+            .map(|h| {
+                y += h;
+                Region::new(self.x, y - h, self.w, h)
+            })
+            .collect()
+    }
+
+    fn columns_weighted(&self, weights: &[f32]) -> Vec<Region> {
+        if weights.len() <= 1 {
+            return vec![*self];
+        }
+        let mut x = self.x;
+        weighted_lengths(self.w, weights)
+            .into_iter()
+            .map(|w| {
+                x += w;
+                Region::new(x - w, self.y, w, self.h)
+            })
+            .collect()
+    }
+}
+
+thread_local! {
+    static WEIGHTS: RefCell<HashMap<WinId, f32>> = RefCell::new(HashMap::new());
+}
+
+const MIN_WEIGHT: f32 = 0.2;
+const MAX_WEIGHT: f32 = 5.0;
+
+/// How much space the client `id` asks for compared to its neighbours, 1.0 by default.
+pub fn weight(id: WinId) -> f32 {
+    WEIGHTS.with(|w| w.borrow().get(&id).copied().unwrap_or(1.0))
+}
+
+/// Grow or shrink the weight of the client `id` by `step`, returning the new weight.
+pub fn change_weight(id: WinId, change: Change, step: f32) -> f32 {
+    let step = if change == Change::More { step } else { -step };
+    let new = (weight(id) + step).clamp(MIN_WEIGHT, MAX_WEIGHT);
+    WEIGHTS.with(|w| w.borrow_mut().insert(id, new));
+    new
+}
+
+/// Give the client `id` its default weight back.
+pub fn reset_weight(id: WinId) {
+    WEIGHTS.with(|w| w.borrow_mut().remove(&id));
+}
+
+pub(crate) fn weights_of(clients: &[&Client]) -> Vec<f32> {
+    clients.iter().map(|c| weight(c.id())).collect()
+}
+
+/// Split `region` into a grid of exactly `n` cells, filling rows first.
+///
+/// The number of columns follows the aspect ratio of `region` so that cells stay close to
+/// square, and the last row is stretched across the full width when it is not full.
+pub(crate) fn grid_regions(region: &Region, n: u32) -> Vec<Region> {
+    let rows = grid_rows(region, n);
+    region
+        .rows(rows.len() as u32)
+        .iter()
+        .zip(rows)
+        .flat_map(|(r, cols)| r.columns(cols))
+        .collect()
+}
+
+/// The number of cells in each row of the grid used by [grid_regions].
+fn grid_rows(region: &Region, n: u32) -> Vec<u32> {
+    if n == 0 {
+        return vec![];
+    }
+    let aspect = region.w as f32 / region.h.max(1) as f32;
+    let cols = ((n as f32 * aspect).sqrt().round() as u32).clamp(1, n);
+    let rows = n.div_ceil(cols);
+    let cols = n.div_ceil(rows);
+
+    (0..rows)
+        .map(|i| {
+            if i == rows - 1 {
+                n - cols * (rows - 1)
+            } else {
+                cols
+            }
+        })
+        .collect()
+}
+
+fn dwindle_overflow(
     clients: &[&Client],
     region: &Region,
-    horizontal: bool,
-    min_size: u32,
-) -> Vec<ResizeAction> {
-    if clients.len() > 1 {
-        if region.w < min_size || region.h < min_size {
+    overflow: Overflow,
+) -> (Vec<ResizeAction>, usize) {
+    match overflow {
+        Overflow::Hide | Overflow::Shrink => (
             clients
                 .iter()
                 .enumerate()
@@ -22,41 +243,750 @@ fn dwindle_recurisive(
                         (c.id(), None)
                     }
                 })
-                .collect()
+                .collect(),
+            clients.len() - 1,
+        ),
+        Overflow::Stack => (
+            clients.iter().map(|c| (c.id(), Some(*region))).collect(),
+            clients.len() - 1,
+        ),
+        Overflow::Grid => (
+            grid_regions(region, clients.len() as u32)
+                .iter()
+                .zip(clients)
+                .map(|(r, c)| (c.id(), Some(*r)))
+                .collect(),
+            0,
+        ),
+    }
+}
+
+/// A side of a region that a client can be placed against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Top,
+    Right,
+    Bottom,
+}
+
+impl Side {
+    fn clockwise(self) -> Self {
+        match self {
+            Side::Left => Side::Top,
+            Side::Top => Side::Right,
+            Side::Right => Side::Bottom,
+            Side::Bottom => Side::Left,
+        }
+    }
+
+    fn counter_clockwise(self) -> Self {
+        match self {
+            Side::Left => Side::Bottom,
+            Side::Bottom => Side::Right,
+            Side::Right => Side::Top,
+            Side::Top => Side::Left,
+        }
+    }
+
+    // dwindle keeps placing clients on the left and on the top
+    fn alternate(self) -> Self {
+        match self {
+            Side::Left | Side::Right => Side::Top,
+            Side::Top | Side::Bottom => Side::Left,
+        }
+    }
+
+    pub(crate) fn splits_width(self) -> bool {
+        matches!(self, Side::Left | Side::Right)
+    }
+}
+
+/// Cut a piece of `ratio` of `region` off against `side`, returning the piece and the rest.
+pub(crate) fn split_off(region: &Region, side: Side, ratio: f32) -> (Region, Region) {
+    let length = if side.splits_width() {
+        region.w
+    } else {
+        region.h
+    };
+    let size = (length as f32 * ratio) as u32;
+    match side {
+        Side::Left => region.split_at_width(size).unwrap(),
+        Side::Top => region.split_at_height(size).unwrap(),
+        Side::Right => {
+            let (rest, piece) = region.split_at_width(region.w - size).unwrap();
+            (piece, rest)
+        }
+        Side::Bottom => {
+            let (rest, piece) = region.split_at_height(region.h - size).unwrap();
+            (piece, rest)
+        }
+    }
+}
+
+/// The corner of the screen a spiral starts winding from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomRight,
+    BottomLeft,
+}
+
+impl Corner {
+    /// The corner that follows this one clockwise.
+    pub fn next(self) -> Self {
+        match self {
+            Corner::TopLeft => Corner::TopRight,
+            Corner::TopRight => Corner::BottomRight,
+            Corner::BottomRight => Corner::BottomLeft,
+            Corner::BottomLeft => Corner::TopLeft,
+        }
+    }
+}
+
+/// The direction a spiral winds in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+}
+
+impl Rotation {
+    /// The opposite rotation.
+    pub fn reverse(self) -> Self {
+        match self {
+            Rotation::Clockwise => Rotation::CounterClockwise,
+            Rotation::CounterClockwise => Rotation::Clockwise,
+        }
+    }
+
+    fn step(self) -> fn(Side) -> Side {
+        match self {
+            Rotation::Clockwise => Side::clockwise,
+            Rotation::CounterClockwise => Side::counter_clockwise,
+        }
+    }
+
+    // The side of the first client so that the first two clients meet at `corner`
+    fn first_side(self, corner: Corner) -> Side {
+        match (self, corner) {
+            (Rotation::Clockwise, Corner::TopLeft) => Side::Left,
+            (Rotation::Clockwise, Corner::TopRight) => Side::Top,
+            (Rotation::Clockwise, Corner::BottomRight) => Side::Right,
+            (Rotation::Clockwise, Corner::BottomLeft) => Side::Bottom,
+            (Rotation::CounterClockwise, Corner::TopLeft) => Side::Top,
+            (Rotation::CounterClockwise, Corner::TopRight) => Side::Right,
+            (Rotation::CounterClockwise, Corner::BottomRight) => Side::Bottom,
+            (Rotation::CounterClockwise, Corner::BottomLeft) => Side::Left,
+        }
+    }
+}
+
+thread_local! {
+    static SPIRAL: Cell<(Corner, Rotation)> = const { Cell::new((Corner::TopLeft, Rotation::Clockwise)) };
+}
+
+/// Set the corner and rotation used by `spiral`.
+pub fn set_spiral(corner: Corner, rotation: Rotation) {
+    SPIRAL.with(|s| s.set((corner, rotation)));
+}
+
+/// Move the start of `spiral` to the next corner clockwise, returning the new corner.
+pub fn cycle_spiral_corner() -> Corner {
+    SPIRAL.with(|s| {
+        let (corner, rotation) = s.get();
+        s.set((corner.next(), rotation));
+        corner.next()
+    })
+}
+
+/// Reverse the rotation of `spiral`, returning the new rotation.
+pub fn reverse_spiral_rotation() -> Rotation {
+    SPIRAL.with(|s| {
+        let (corner, rotation) = s.get();
+        s.set((corner, rotation.reverse()));
+        rotation.reverse()
+    })
+}
+
+fn dwindle_recurisive(
+    clients: &[&Client],
+    region: &Region,
+    side: Side,
+    next: fn(Side) -> Side,
+    min_size: u32,
+    overflow: Overflow,
+) -> (Vec<ResizeAction>, usize) {
+    if clients.len() > 1 {
+        if region.w < min_size || region.h < min_size {
+            dwindle_overflow(clients, region, overflow)
         } else {
-            let split = ((if horizontal { region.w } else { region.h } as f32) / 2.) as u32;
-            let (main, other) = if horizontal {
-                region.split_at_width(split)
-            } else {
-                region.split_at_height(split)
-            }
-            .unwrap();
+            let (w0, w1) = (weight(clients[0].id()), weight(clients[1].id()));
+            let (main, other) = split_off(region, side, w0 / (w0 + w1));
 
-            let mut vec =
-                dwindle_recurisive(&clients[..clients.len() - 1], &other, !horizontal, min_size);
-            vec.push((clients.last().unwrap().id(), Some(main)));
-            vec
+            let (rest, hidden) =
+                dwindle_recurisive(&clients[1..], &other, next(side), next, min_size, overflow);
+            let mut vec = vec![(clients[0].id(), Some(main))];
+            vec.extend(rest);
+            (vec, hidden)
         }
     } else {
-        clients
-            .get(0)
-            .map(|c| vec![(c.id(), Some(*region))])
-            .unwrap_or(Vec::new())
+        (
+            clients
+                .first()
+                .map(|c| vec![(c.id(), Some(*region))])
+                .unwrap_or_default(),
+            0,
+        )
     }
 }
 
+/// Run `dwindle_recurisive`, halving `min_size` while clients overflow when shrinking.
+fn dwindle_adaptive(
+    clients: &[&Client],
+    region: &Region,
+    side: Side,
+    next: fn(Side) -> Side,
+) -> (Vec<ResizeAction>, usize) {
+    let (overflow, mut min_size) = DWINDLE_OVERFLOW.with(|o| o.get());
+    loop {
+        let (vec, hidden) = dwindle_recurisive(clients, region, side, next, min_size, overflow);
+        if hidden == 0 || overflow != Overflow::Shrink || min_size <= 1 {
+            return (vec, hidden);
+        }
+        min_size /= 2;
+    }
+}
+
+/// Place the first `max_main` clients against `side` taking `ratio` of the region and let
+/// the remaining clients wind through the rest of it.
+fn dwindle_with_main(
+    clients: &[&Client],
+    monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+    side: Side,
+    next: fn(Side) -> Side,
+) -> Vec<ResizeAction> {
+    let n = clients.len() as u32;
+
+    if max_main == 0 {
+        let (vec, hidden) = dwindle_adaptive(clients, monitor_region, side, next);
+        set_hidden_clients(clients, hidden);
+        return vec;
+    }
+
+    set_hidden_clients(clients, 0);
+    if n <= max_main {
+        return monitor_region
+            .rows_weighted(&weights_of(clients))
+            .iter()
+            .zip(clients)
+            .map(|(r, c)| (c.id(), Some(*r)))
+            .collect();
+    }
+
+    let (main, stack) = split_off(monitor_region, side, ratio);
+    let main_weights = weights_of(&clients[..max_main as usize]);
+    let main_regions = if side.splits_width() {
+        main.rows_weighted(&main_weights)
+    } else {
+        main.columns_weighted(&main_weights)
+    };
+
+    let mut vec: Vec<ResizeAction> = main_regions
+        .iter()
+        .zip(clients)
+        .map(|(r, c)| (c.id(), Some(*r)))
+        .collect();
+    let (rest, hidden) = dwindle_adaptive(&clients[max_main as usize..], &stack, next(side), next);
+    set_hidden_clients(clients, hidden);
+    vec.extend(rest);
+    vec
+}
+
 /**
  * A layout based on the dwindle layout from AwesomeWM.
  *
- * The second region is recursively split in two other regions, alternating between
- * splitting horizontally and vertically.
+ * The first `max_main` clients share a main region on the left taking `ratio` of the
+ * screen width. The remaining region is recursively split in two other regions,
+ * alternating between splitting horizontally and vertically. With `max_main` set to
+ * zero the whole screen dwindles.
+ *
+ * Once a region gets smaller than the minimum size the remaining clients are handled
+ * as set by [set_dwindle_overflow] and the number of clients that can not be seen is
+ * available through [hidden_clients].
+ *
+ * Every split is biased by the weights of the two clients meeting at it, see
+ * [change_weight], so growing a client takes space from both of its neighbours.
  */
 pub fn dwindle(
     clients: &[&Client],
     _: Option<WinId>,
     monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    dwindle_with_main(
+        clients,
+        monitor_region,
+        max_main,
+        ratio,
+        Side::Left,
+        Side::alternate,
+    )
+}
+
+/**
+ * A layout based on the spiral layout from AwesomeWM.
+ *
+ * Works like [dwindle] but the side each new client is placed against keeps rotating
+ * through all four sides, so the clients wind inwards starting from the corner and in
+ * the rotation set by [set_spiral].
+ */
+pub fn spiral(
+    clients: &[&Client],
+    _: Option<WinId>,
+    monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    let (corner, rotation) = SPIRAL.with(|s| s.get());
+    dwindle_with_main(
+        clients,
+        monitor_region,
+        max_main,
+        ratio,
+        rotation.first_side(corner),
+        rotation.step(),
+    )
+}
+
+/**
+ * A layout that tiles every client as an even grid.
+ *
+ * Rows and columns are picked from the number of clients and the aspect ratio of the
+ * screen, and the last row stretches to fill the width. With a non-zero `max_main` the
+ * first `max_main` clients share one big cell on the left taking `ratio` of the screen
+ * width and the grid fills the rest.
+ */
+pub fn grid(
+    clients: &[&Client],
+    _: Option<WinId>,
+    monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    let n = clients.len() as u32;
+
+    if max_main == 0 || n <= max_main {
+        return grid_regions(monitor_region, n)
+            .iter()
+            .zip(clients)
+            .map(|(r, c)| (c.id(), Some(*r)))
+            .collect();
+    }
+
+    let (main, stack) = split_off(monitor_region, Side::Left, ratio);
+
+    main.rows_weighted(&weights_of(&clients[..max_main as usize]))
+        .into_iter()
+        .chain(grid_regions(&stack, n - max_main))
+        .zip(clients)
+        .map(|(r, c)| (c.id(), Some(r)))
+        .collect()
+}
+
+thread_local! {
+    static CENTERED_MAIN_ALWAYS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Toggle whether `centered_main` keeps the main column centered with a single stack
+/// client, returning the new setting.
+pub fn toggle_centered_main_always() -> bool {
+    CENTERED_MAIN_ALWAYS.with(|c| {
+        c.set(!c.get());
+        c.get()
+    })
+}
+
+/**
+ * A layout for wide screens that keeps the main clients in a centered column.
+ *
+ * The main column takes `ratio` of the screen width and the stack clients alternate
+ * between a right and a left column. With a single stack client the main column moves
+ * to the left, unless [toggle_centered_main_always] has been used to keep it centered.
+ */
+pub fn centered_main(
+    clients: &[&Client],
+    _: Option<WinId>,
+    monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    let n = clients.len() as u32;
+
+    if n <= max_main || max_main == 0 {
+        return monitor_region
+            .rows_weighted(&weights_of(clients))
+            .iter()
+            .zip(clients)
+            .map(|(r, c)| (c.id(), Some(*r)))
+            .collect();
+    }
+
+    let n_stack = n - max_main;
+    if n_stack == 1 && !CENTERED_MAIN_ALWAYS.with(|c| c.get()) {
+        let (main, stack) = split_off(monitor_region, Side::Left, ratio);
+        return main
+            .rows_weighted(&weights_of(&clients[..max_main as usize]))
+            .into_iter()
+            .chain(Some(stack))
+            .zip(clients)
+            .map(|(r, c)| (c.id(), Some(r)))
+            .collect();
+    }
+
+    let (x, y, w, h) = monitor_region.values();
+    let main_w = (w as f32 * ratio) as u32;
+    let side_w = (w - main_w) / 2;
+    let main = Region::new(x + side_w, y, main_w, h);
+    let left = Region::new(x, y, side_w, h);
+    let right = Region::new(x + side_w + main_w, y, w - side_w - main_w, h);
+
+    let (main_clients, stack_clients) = clients.split_at(max_main as usize);
+    let stack_weights = weights_of(stack_clients);
+    let right_weights: Vec<f32> = stack_weights.iter().copied().step_by(2).collect();
+    let left_weights: Vec<f32> = stack_weights.iter().copied().skip(1).step_by(2).collect();
+    let mut right_rows = right.rows_weighted(&right_weights).into_iter();
+    let mut left_rows = left.rows_weighted(&left_weights).into_iter();
+    let stack = (0..n_stack).filter_map(|i| {
+        if i % 2 == 0 {
+            right_rows.next()
+        } else {
+            left_rows.next()
+        }
+    });
+
+    main.rows_weighted(&weights_of(main_clients))
+        .into_iter()
+        .chain(stack)
+        .zip(clients)
+        .map(|(r, c)| (c.id(), Some(r)))
+        .collect()
+}
+
+/// Regions for `clients` spread over `cols` columns of `region`, filling each column
+/// before moving to the next and putting the extra clients in the leftmost columns.
+fn column_regions(region: &Region, clients: &[&Client], cols: u32) -> Vec<Region> {
+    let n = clients.len() as u32;
+    let cols = cols.clamp(1, n.max(1));
+    let mut rest = clients;
+    region
+        .columns(cols)
+        .iter()
+        .enumerate()
+        .flat_map(|(i, r)| {
+            let extra = if (i as u32) < n % cols { 1 } else { 0 };
+            let (column, others) = rest.split_at((n / cols + extra) as usize);
+            rest = others;
+            r.rows_weighted(&weights_of(column))
+        })
+        .take(n as usize)
+        .collect()
+}
+
+fn three_column_with(
+    clients: &[&Client],
+    monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+    main_in_middle: bool,
+) -> Vec<ResizeAction> {
+    let n = clients.len() as u32;
+
+    if n <= max_main || max_main == 0 {
+        return monitor_region
+            .rows_weighted(&weights_of(clients))
+            .iter()
+            .zip(clients)
+            .map(|(r, c)| (c.id(), Some(*r)))
+            .collect();
+    }
+
+    let n_stack = n - max_main;
+    let (main_clients, stack_clients) = clients.split_at(max_main as usize);
+    let (x, y, w, h) = monitor_region.values();
+    let main_w = (w as f32 * ratio) as u32;
+    let (main, stack) = if n_stack == 1 || !main_in_middle {
+        let (main, stack) = split_off(monitor_region, Side::Left, ratio);
+        (main, column_regions(&stack, stack_clients, 2))
+    } else {
+        let side_w = (w - main_w) / 2;
+        let left = Region::new(x, y, side_w, h);
+        let right = Region::new(x + side_w + main_w, y, w - side_w - main_w, h);
+        let (right_clients, left_clients) =
+            stack_clients.split_at((n_stack - n_stack / 2) as usize);
+        (
+            Region::new(x + side_w, y, main_w, h),
+            right
+                .rows_weighted(&weights_of(right_clients))
+                .into_iter()
+                .chain(left.rows_weighted(&weights_of(left_clients)))
+                .collect(),
+        )
+    };
+
+    main.rows_weighted(&weights_of(main_clients))
+        .into_iter()
+        .chain(stack)
+        .zip(clients)
+        .map(|(r, c)| (c.id(), Some(r)))
+        .collect()
+}
+
+/**
+ * A layout with the main clients in a column on the left taking `ratio` of the screen
+ * width and the stack split over two columns to its right.
+ */
+pub fn three_column(
+    clients: &[&Client],
+    _: Option<WinId>,
+    monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    three_column_with(clients, monitor_region, max_main, ratio, false)
+}
+
+/**
+ * Like [three_column] but with the main column in the middle of the screen, the first
+ * half of the stack on its right and the second half on its left.
+ */
+pub fn three_column_mid(
+    clients: &[&Client],
+    _: Option<WinId>,
+    monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    three_column_with(clients, monitor_region, max_main, ratio, true)
+}
+
+/**
+ * A layout with `max_main` equally sized columns, so that `update_max_main` adds and
+ * removes columns. Clients fill the columns from left to right and `ratio` is ignored.
+ */
+pub fn columns(
+    clients: &[&Client],
+    _: Option<WinId>,
+    monitor_region: &Region,
+    max_main: u32,
+    _: f32,
+) -> Vec<ResizeAction> {
+    column_regions(monitor_region, clients, max_main)
+        .iter()
+        .zip(clients)
+        .map(|(r, c)| (c.id(), Some(*r)))
+        .collect()
+}
+
+/// The strip of tabs that `tabbed` reserved on a workspace the last time it ran.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabStrip {
+    pub region: Region,
+    pub clients: Vec<WinId>,
+    pub focused: WinId,
+}
+
+thread_local! {
+    static TAB_HEIGHT: Cell<u32> = const { Cell::new(18) };
+    static TAB_STRIPS: RefCell<HashMap<usize, TabStrip>> = RefCell::new(HashMap::new());
+}
+
+/// Set the height of the strip reserved by `tabbed`.
+pub fn set_tab_height(height: u32) {
+    TAB_HEIGHT.with(|h| h.set(height));
+}
+
+/// The tab strip left by the last run of `tabbed` on `workspace`, if any.
+pub fn take_tab_strip(workspace: usize) -> Option<TabStrip> {
+    TAB_STRIPS.with(|t| t.borrow_mut().remove(&workspace))
+}
+
+/**
+ * A monocle layout that reserves a strip at the top of the screen for tabs.
+ *
+ * The focused client takes the rest of the screen and all other clients are unmapped.
+ * The strip itself is drawn by the `TabBar` hook from what is left in [take_tab_strip].
+ */
+pub fn tabbed(
+    clients: &[&Client],
+    focused: Option<WinId>,
+    monitor_region: &Region,
     _: u32,
     _: f32,
 ) -> Vec<ResizeAction> {
-    dwindle_recurisive(clients, monitor_region, true, 50)
+    let first = match clients.first() {
+        Some(c) => c,
+        None => return vec![],
+    };
+    let fid = focused
+        .filter(|id| clients.iter().any(|c| c.id() == *id))
+        .unwrap_or_else(|| first.id());
+
+    let height = TAB_HEIGHT.with(|h| h.get()).min(monitor_region.h);
+    let (strip, body) = monitor_region.split_at_height(height).unwrap();
+    TAB_STRIPS.with(|t| {
+        t.borrow_mut().insert(
+            first.workspace(),
+            TabStrip {
+                region: strip,
+                clients: clients.iter().map(|c| c.id()).collect(),
+                focused: fid,
+            },
+        )
+    });
+
+    clients
+        .iter()
+        .map(|c| {
+            let cid = c.id();
+            if cid == fid {
+                (cid, Some(body))
+            } else {
+                (cid, None)
+            }
+        })
+        .collect()
+}
+
+/// Weights for `n` pieces giving the one at `index` a `share` of the total, or at least as
+/// much as the others.
+fn enlarged_weights(n: usize, index: Option<usize>, share: f32) -> Vec<f32> {
+    if n <= 1 {
+        return vec![1.0; n];
+    }
+    let share = share.clamp(1.0 / n as f32, 0.95);
+    let enlarged = share * (n as f32 - 1.0) / (1.0 - share);
+    (0..n)
+        .map(|i| if Some(i) == index { enlarged } else { 1.0 })
+        .collect()
+}
+
+/**
+ * A grid in which the focused client grows to a `ratio` share of the screen, the golden
+ * ratio by default, and the other clients shrink around it.
+ *
+ * The row of the focused client takes `ratio` of the height and the focused client takes
+ * `ratio` of the width of its row. Use it with `follow_focus` so that it is laid out again
+ * every time the focus moves. `max_main` is ignored.
+ */
+pub fn golden(
+    clients: &[&Client],
+    focused: Option<WinId>,
+    monitor_region: &Region,
+    _: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    let rows = grid_rows(monitor_region, clients.len() as u32);
+    let index = focused.and_then(|id| clients.iter().position(|c| c.id() == id));
+
+    let mut start = 0;
+    let rows: Vec<(usize, Option<usize>)> = rows
+        .iter()
+        .map(|n| {
+            let n = *n as usize;
+            let focused_col = index
+                .filter(|i| (start..start + n).contains(i))
+                .map(|i| i - start);
+            start += n;
+            (n, focused_col)
+        })
+        .collect();
+    let focused_row = rows.iter().position(|(_, col)| col.is_some());
+
+    monitor_region
+        .rows_weighted(&enlarged_weights(rows.len(), focused_row, ratio))
+        .iter()
+        .zip(rows)
+        .flat_map(|(r, (n, col))| r.columns_weighted(&enlarged_weights(n, col, ratio)))
+        .zip(clients)
+        .map(|(r, c)| (c.id(), Some(r)))
+        .collect()
+}
+
+thread_local! {
+    static DECK_SLIVER: Cell<u32> = const { Cell::new(20) };
+    static DECK_OPEN: RefCell<HashMap<usize, WinId>> = RefCell::new(HashMap::new());
+}
+
+/// Set the height of the strips `deck` leaves for the stack clients that are not open.
+pub fn set_deck_sliver(height: u32) {
+    DECK_SLIVER.with(|s| s.set(height));
+}
+
+/**
+ * A layout based on the deck patch for dwm, with the stack as an accordion.
+ *
+ * The first `max_main` clients share a main region on the left taking `ratio` of the
+ * screen width. In the stack only the focused client is open and every other one is
+ * squeezed to a thin sliver, see [set_deck_sliver], above or below it following the
+ * stack order. While a main client has focus the stack keeps the last open client.
+ */
+pub fn deck(
+    clients: &[&Client],
+    focused: Option<WinId>,
+    monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    let n = clients.len() as u32;
+
+    if n <= max_main || max_main == 0 {
+        return monitor_region
+            .rows_weighted(&weights_of(clients))
+            .iter()
+            .zip(clients)
+            .map(|(r, c)| (c.id(), Some(*r)))
+            .collect();
+    }
+
+    let (main_clients, stack_clients) = clients.split_at(max_main as usize);
+    let (main, stack) = split_off(monitor_region, Side::Left, ratio);
+
+    let wix = clients[0].workspace();
+    let open = DECK_OPEN.with(|d| {
+        let mut open = d.borrow_mut();
+        let in_stack = |id: &WinId| stack_clients.iter().any(|c| c.id() == *id);
+        match focused.filter(in_stack) {
+            Some(id) => {
+                open.insert(wix, id);
+            }
+            None => {
+                if !open.get(&wix).map(in_stack).unwrap_or(false) {
+                    open.insert(wix, stack_clients[0].id());
+                }
+            }
+        }
+        open[&wix]
+    });
+
+    let n_stack = stack_clients.len() as u32;
+    let sliver = DECK_SLIVER.with(|s| s.get()).min(stack.h / n_stack);
+    let open_h = stack.h - sliver * (n_stack - 1);
+    let mut y = stack.y;
+    let stack_regions = stack_clients.iter().map(|c| {
+        let h = if c.id() == open { open_h } else { sliver };
+        y += h;
+        Region::new(stack.x, y - h, stack.w, h)
+    });
+
+    main.rows_weighted(&weights_of(main_clients))
+        .into_iter()
+        .chain(stack_regions)
+        .zip(clients)
+        .map(|(r, c)| (c.id(), Some(r)))
+        .collect()
 }
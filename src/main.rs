@@ -15,25 +15,64 @@ use penrose::{
         manager::WindowManager,
         layout::{bottom_stack, monocle, side_stack, Layout, LayoutConf},
         ring::Selector,
+        xconnection::XConn,
     },
-    draw::{bar::dwm_bar, Color, TextStyle},
+    draw::{Color, TextStyle},
     logging_error_handler,
     xcb::{new_xcb_backed_window_manager, XcbDraw, XcbHooks},
     Backward, Forward, Less, More,
 };
+use log::error;
 use simplelog::{LevelFilter, SimpleLogger};
 use std::{
     // io::Read,
     // process::{Command, Stdio},
-    convert::TryFrom,
     collections::HashMap,
+    path::PathBuf,
     thread, time, env,
 };
 // use dirs::home_dir;
+#[macro_use]
+mod gaps;
+use gaps::{change_gaps, set_gaps, toggle_gaps, toggle_smart_gaps};
+mod autostart;
+use autostart::{Autostart, Command};
+mod bsp;
+use bsp::{bsp, bsp_flip, bsp_preselect, bsp_resize, bsp_rotate, focus_dwindle, Split, TreeKey};
 mod hooks;
-use hooks::{CenterFloat, StartupScript};
+use hooks::{BspFocus, ForgetWeights};
 mod layouts;
-use layouts::dwindle;
+#[cfg(test)]
+mod layout_tests;
+use layouts::{
+    centered_main, change_weight, columns, cycle_dwindle_overflow, cycle_spiral_corner, deck,
+    dwindle, golden, grid, reset_weight, reverse_spiral_rotation, set_deck_sliver,
+    set_dwindle_overflow, set_spiral, spiral, tabbed, three_column, three_column_mid,
+    toggle_centered_main_always, Corner, Overflow, Rotation, Side,
+};
+mod tabs;
+use tabs::TabBar;
+#[macro_use]
+mod transform;
+use transform::{toggle_flip, toggle_mirror, toggle_transpose};
+mod declarative;
+use declarative::load_layouts;
+mod preview;
+use preview::Preview;
+mod floating;
+#[cfg(test)]
+mod floating_tests;
+use floating::{FloatSize, Placement};
+mod rules;
+#[cfg(test)]
+mod rules_tests;
+use rules::{dialog_rules, load_rules, Rule, Rules};
+mod workspaces;
+#[cfg(test)]
+mod workspace_tests;
+use workspaces::{LayoutSettings, WorkspaceLayouts};
+mod widgets;
+use widgets::status_bar;
 
 const HEIGHT: usize = 18;
 
@@ -70,8 +109,8 @@ fn show_window_text(
     parent: xcb::ffi::xproto::xcb_window_t,
     text: &str) -> Option<xcb::ffi::xproto::xcb_window_t> {
     let font = conn.generate_id();
-    if let Ok(_) = xcb::xproto::open_font_checked(
-        conn, font, "-*-fixed-medium-*-*-*-18-*-*-*-*-*-*-*").request_check()
+    if xcb::xproto::open_font_checked(
+        conn, font, "-*-fixed-medium-*-*-*-18-*-*-*-*-*-*-*").request_check().is_ok()
     {
         let window = conn.generate_id();
         xcb::xproto::create_window(
@@ -110,62 +149,166 @@ fn show_window_text(
 }
 
 
+/// Where our configuration files live, `$XDG_CONFIG_HOME/penrose` by default.
+fn config_dir() -> PathBuf {
+    env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".config"))
+        .join("penrose")
+}
+
+/// Where state kept across restarts lives, `$XDG_STATE_HOME/penrose` by default.
+fn state_dir() -> PathBuf {
+    env::var("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".local/state"))
+        .join("penrose")
+}
+
+fn relayout_screens<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    for screen_index in 0..wm.n_screens() {
+        wm.layout_screen(screen_index)?;
+    }
+    Ok(())
+}
+
+/// The split tree the focused client is laid out with, if the active layout keeps one.
+fn bsp_tree<X: XConn>(wm: &WindowManager<X>) -> Option<(TreeKey, WinId)> {
+    let split = match wm.current_layout_symbol() {
+        "[bsp]" => Split::Longest,
+        "[fdwl]" => Split::Alternate,
+        _ => return None,
+    };
+    let wix = *wm.focused_workspaces().get(wm.active_screen_index())?;
+    Some(((wix, split), wm.focused_client_id()?))
+}
+
 fn main() -> penrose::Result<()> {
     if let Err(e) = SimpleLogger::init(LevelFilter::Info, simplelog::Config::default()) {
         panic!("unable to set log level: {}", e);
     }
 
-    // spawn_for_output("xrandr-monitors --run")?;
-    // spawn_with_args(
-    //     "dunst",
-    //     &["-history_length", "100", "-history_key", "mod4+ccedilla",
-    //       "-key", "mod4+shift+ccedilla", "-context_key", "mod4+shift+ntilde",
-    //       "-lto", "10s", "-nto", "15s", "-cto", "20s",
-    //       "-show_age_threshold", "1m", "-idle_threshold", "10m",
-    //       "-format", "%a: %s %n\\n%b"])?;
-    // spawn("compton -b --config /dev/null --backend xrender")?;
-    // spawn(format!("feh --bg-scale --randomize {}/Pictures/wallpapers/",
-    //                home_dir().unwrap().display()))?;
-    // spawn_with_args("keynav", &["loadconfig ~/.config/keynav/keynavrc"])?;
+    set_dwindle_overflow(Overflow::Stack, 50);
+    set_spiral(Corner::TopLeft, Rotation::Clockwise);
+    set_gaps(5, 10, true);
+    set_deck_sliver(HEIGHT as u32);
+    let mut layouts = vec![
+        Layout::new("[side]", LayoutConf::default(), gapped!(transformed!(side_stack)), 1, 0.6),
+        Layout::new("[mono]", LayoutConf{
+            floating: false, gapless: true, follow_focus: true, allow_wrapping: true,
+        }, monocle, 1, 0.6),
+        Layout::new("[papr]", LayoutConf{
+            floating: false, gapless: true, follow_focus: true, allow_wrapping: false,
+        }, transformed!(paper), 1, 0.6),
+        Layout::new("[deck]", LayoutConf{
+            floating: false, gapless: false, follow_focus: true, allow_wrapping: true,
+        }, gapped!(transformed!(deck)), 1, 0.6),
+        Layout::new("[tabs]", LayoutConf{
+            floating: false, gapless: true, follow_focus: true, allow_wrapping: true,
+        }, tabbed, 1, 0.6),
+        Layout::new("[dwdl]", LayoutConf::default(), gapped!(transformed!(dwindle)), 1, 0.6),
+        Layout::new("[fdwl]", LayoutConf::default(), gapped!(transformed!(focus_dwindle)), 1, 0.6),
+        Layout::new("[sprl]", LayoutConf::default(), gapped!(transformed!(spiral)), 1, 0.6),
+        Layout::new("[cntr]", LayoutConf::default(), gapped!(transformed!(centered_main)), 1, 0.5),
+        Layout::new("[3col]", LayoutConf::default(), gapped!(transformed!(three_column)), 1, 0.4),
+        Layout::new("[3mid]", LayoutConf::default(), gapped!(transformed!(three_column_mid)), 1, 0.4),
+        Layout::new("[cols]", LayoutConf::default(), gapped!(transformed!(columns)), 3, 0.6),
+        Layout::new("[grid]", LayoutConf::default(), gapped!(transformed!(grid)), 0, 0.6),
+        Layout::new("[bsp]", LayoutConf::default(), gapped!(bsp), 1, 0.6),
+        Layout::new("[gold]", LayoutConf{
+            floating: false, gapless: false, follow_focus: true, allow_wrapping: true,
+        }, gapped!(transformed!(golden)), 1, 0.618),
+        Layout::new("[botm]", LayoutConf::default(), gapped!(transformed!(bottom_stack)), 1, 0.6),
+    ];
+    match load_layouts(config_dir().join("layouts.toml")) {
+        Ok(declared) => layouts.extend(declared),
+        Err(e) => error!("unable to load layouts.toml: {}", e),
+    }
+    let mut args = env::args().skip(1);
+    if args.next().as_deref() == Some("--preview-layout") {
+        print!("{}", Preview::from_args(args)?.render(layouts)?);
+        return Ok(());
+    }
     let config = Config::default()
         .builder()
         .workspaces(vec!["1", "2", "3", "4", "5", "6", "7", "8", "9"])
         .floating_classes(vec!["rofi", "dmenu", "dunst", "yad", "gcr-prompter"])
-        .layouts(vec![
-            Layout::new("[side]", LayoutConf::default(), side_stack, 1, 0.6),
-            Layout::new("[mono]", LayoutConf{
-                floating: false, gapless: true, follow_focus: true, allow_wrapping: true,
-            }, monocle, 1, 0.6),
-            Layout::new("[papr]", LayoutConf{
-                floating: false, gapless: true, follow_focus: true, allow_wrapping: false,
-            }, paper, 1, 0.6),
-            Layout::new("[dwdl]", LayoutConf::default(), dwindle, 1, 0.6),
-            Layout::new("[botm]", LayoutConf::default(), bottom_stack, 1, 0.6),
-        ])
+        .gap_px(0)
+        .layouts(layouts)
         .build()
         .unwrap();
-    let bar = dwm_bar(
+    let style = TextStyle {
+        font: FONT.to_string(),
+        point_size: 10,
+        fg: Color::from(WHITE),
+        bg: Some(Color::from(BLACK)),
+        padding: (2.0, 2.0),
+    };
+    let bar = status_bar(
         XcbDraw::new()?,
         HEIGHT,
-        &TextStyle {
-            font: FONT.to_string(),
-            point_size: 10,
-            fg: Color::try_from(WHITE).unwrap(),
-            bg: Some(Color::try_from(BLACK).unwrap()),
-            padding: (2.0, 2.0),
-        },
+        &style,
         BLUE,
         GREY,
         config.workspaces().clone(),
     )?;
 
+    let mut rules = dialog_rules();
+    rules.extend(vec![
+        Rule::new()
+            .instances(config.floating_classes())
+            .center(FloatSize::scale(0.9).with_hints().keep_aspect()),
+        Rule::new()
+            .instance("gcr-prompter")
+            .center(FloatSize::absolute(480, 240).with_hints().max(800, 400))
+            .place(Placement::PointerScreen),
+    ]);
+    match load_rules(config_dir().join("rules.toml")) {
+        Ok(declared) => rules.extend(declared),
+        Err(e) => error!("unable to load rules.toml: {}", e),
+    }
+
     let sp_term = Scratchpad::new("alacritty", 0.8, 0.8);
 
     let hooks: XcbHooks = vec![
-        Box::new(StartupScript::new()),
+        Autostart::new(vec![
+            // the bar expects numbers with a decimal point
+            Command::new("lbarstat").env("LC_NUMERIC", "C").restart().supervise(5),
+            Command::new("xrandr-monitors").args(&["--run"]).once(),
+            Command::new("dunst")
+                .args(&[
+                    "-history_length", "100", "-history_key", "mod4+ccedilla",
+                    "-key", "mod4+shift+ccedilla", "-context_key", "mod4+shift+ntilde",
+                    "-lto", "10s", "-nto", "15s", "-cto", "20s",
+                    "-show_age_threshold", "1m", "-idle_threshold", "10m",
+                    "-format", "%a: %s %n\\n%b",
+                ])
+                .unless_running("dunst")
+                .supervise(5),
+            Command::new("compton")
+                .args(&["-b", "--config", "/dev/null", "--backend", "xrender"])
+                .unless_running("compton")
+                .supervise(5),
+            Command::shell("feh", "feh --bg-scale --randomize ~/Pictures/wallpapers/")
+                .once()
+                .delay(time::Duration::from_secs(1)),
+            Command::new("keynav")
+                .args(&["loadconfig ~/.config/keynav/keynavrc"])
+                .unless_running("keynav"),
+        ]),
+        Box::new(ForgetWeights::new()),
+        Box::new(BspFocus::new()),
+        WorkspaceLayouts::new(
+            vec![
+                ("2", LayoutSettings::new("[mono]", 1, 0.6)),
+                ("9", LayoutSettings::new("[dwdl]", 1, 0.6)),
+            ],
+            state_dir().join("workspaces.toml"),
+        ),
         sp_term.get_hook(),
         Box::new(bar),
-        CenterFloat::new(config.floating_classes().clone(), 0.9),
+        Box::new(TabBar::new(XcbDraw::new()?, HEIGHT, &style, BLUE)?),
+        Rules::new(rules, state_dir().join("floating.toml")),
     ];
 
     let cycle_screen_direction = match env::var("MONITORS_LAYOUT") {
@@ -360,8 +503,8 @@ fn main() -> penrose::Result<()> {
                 let millis = time::Duration::from_millis(10);
                 loop
                 {
-                    if let Ok(_) = xcb::ungrab_keyboard_checked(
-                        &conn, xcb::CURRENT_TIME).request_check()
+                    if xcb::ungrab_keyboard_checked(
+                        &conn, xcb::CURRENT_TIME).request_check().is_ok()
                     {
                         break;
                     }
@@ -515,6 +658,133 @@ fn main() -> penrose::Result<()> {
             }
             Ok(())
         });
+        "M-A-o" => Box::new(|wm: &mut WindowManager<_>| {
+            let overflow = cycle_dwindle_overflow();
+            wm.log(format!("dwindle overflow: {:?}", overflow))?;
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-A-s" => Box::new(|wm: &mut WindowManager<_>| {
+            let corner = cycle_spiral_corner();
+            wm.log(format!("spiral corner: {:?}", corner))?;
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-A-r" => Box::new(|wm: &mut WindowManager<_>| {
+            let rotation = reverse_spiral_rotation();
+            wm.log(format!("spiral rotation: {:?}", rotation))?;
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-A-c" => Box::new(|wm: &mut WindowManager<_>| {
+            let always = toggle_centered_main_always();
+            wm.log(format!("centered main always: {}", always))?;
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-C-h" => Box::new(|wm: &mut WindowManager<_>| {
+            if let Some((tree, id)) = bsp_tree(wm) {
+                bsp_preselect(tree, id, Side::Left);
+            }
+            Ok(())
+        });
+        "M-C-j" => Box::new(|wm: &mut WindowManager<_>| {
+            if let Some((tree, id)) = bsp_tree(wm) {
+                bsp_preselect(tree, id, Side::Bottom);
+            }
+            Ok(())
+        });
+        "M-C-k" => Box::new(|wm: &mut WindowManager<_>| {
+            if let Some((tree, id)) = bsp_tree(wm) {
+                bsp_preselect(tree, id, Side::Top);
+            }
+            Ok(())
+        });
+        "M-C-l" => Box::new(|wm: &mut WindowManager<_>| {
+            if let Some((tree, id)) = bsp_tree(wm) {
+                bsp_preselect(tree, id, Side::Right);
+            }
+            Ok(())
+        });
+        "M-C-r" => Box::new(|wm: &mut WindowManager<_>| {
+            if let Some((tree, id)) = bsp_tree(wm) {
+                bsp_rotate(tree, id);
+            }
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-C-x" => Box::new(|wm: &mut WindowManager<_>| {
+            if let Some((tree, id)) = bsp_tree(wm) {
+                bsp_flip(tree, id, true);
+            }
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-C-y" => Box::new(|wm: &mut WindowManager<_>| {
+            if let Some((tree, id)) = bsp_tree(wm) {
+                bsp_flip(tree, id, false);
+            }
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-C-equal" => Box::new(|wm: &mut WindowManager<_>| {
+            if let Some((tree, id)) = bsp_tree(wm) {
+                bsp_resize(tree, id, More, 0.05);
+            }
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-C-minus" => Box::new(|wm: &mut WindowManager<_>| {
+            if let Some((tree, id)) = bsp_tree(wm) {
+                bsp_resize(tree, id, Less, 0.05);
+            }
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-A-equal" => Box::new(|wm: &mut WindowManager<_>| {
+            let gaps = change_gaps(More, 2);
+            wm.log(format!("gaps: {:?}", gaps))?;
+            relayout_screens(wm)
+        });
+        "M-A-minus" => Box::new(|wm: &mut WindowManager<_>| {
+            let gaps = change_gaps(Less, 2);
+            wm.log(format!("gaps: {:?}", gaps))?;
+            relayout_screens(wm)
+        });
+        "M-A-g" => Box::new(|wm: &mut WindowManager<_>| {
+            let enabled = toggle_gaps();
+            wm.log(format!("gaps enabled: {}", enabled))?;
+            relayout_screens(wm)
+        });
+        "M-A-S-g" => Box::new(|wm: &mut WindowManager<_>| {
+            let smart = toggle_smart_gaps();
+            wm.log(format!("smart gaps: {}", smart))?;
+            relayout_screens(wm)
+        });
+        "M-A-x" => Box::new(|wm: &mut WindowManager<_>| {
+            let transform = toggle_mirror(wm.focused_workspaces()[wm.active_screen_index()]);
+            wm.log(format!("layout transform: {:?}", transform))?;
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-A-y" => Box::new(|wm: &mut WindowManager<_>| {
+            let transform = toggle_flip(wm.focused_workspaces()[wm.active_screen_index()]);
+            wm.log(format!("layout transform: {:?}", transform))?;
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-A-t" => Box::new(|wm: &mut WindowManager<_>| {
+            let transform = toggle_transpose(wm.focused_workspaces()[wm.active_screen_index()]);
+            wm.log(format!("layout transform: {:?}", transform))?;
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-equal" => Box::new(|wm: &mut WindowManager<_>| {
+            if let Some(id) = wm.focused_client_id() {
+                change_weight(id, More, 0.25);
+            }
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-minus" => Box::new(|wm: &mut WindowManager<_>| {
+            if let Some(id) = wm.focused_client_id() {
+                change_weight(id, Less, 0.25);
+            }
+            wm.layout_screen(wm.active_screen_index())
+        });
+        "M-0" => Box::new(|wm: &mut WindowManager<_>| {
+            if let Some(id) = wm.focused_client_id() {
+                reset_weight(id);
+            }
+            wm.layout_screen(wm.active_screen_index())
+        });
         "M-bracketright" => run_internal!(cycle_layout, Forward);
         "M-bracketleft" => run_internal!(cycle_layout, Backward);
         "M-A-k" => run_internal!(update_max_main, More);
@@ -582,6 +852,7 @@ fn main() -> penrose::Result<()> {
     };
 
     let mut wm = new_xcb_backed_window_manager(config, hooks, logging_error_handler())?;
+    wm.set_root_window_name("root")?;
     wm.grab_keys_and_run(key_bindings, mouse_bindings)?;
 
     Ok(())
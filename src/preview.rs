@@ -0,0 +1,226 @@
+//! Draw the result of a layout as ASCII art, without an X server:
+//!
+//! ```text
+//! penrose_setup --preview-layout dwdl --clients 4 --size 1920x1080 --focus 2
+//! ```
+//!
+//! Every visible client is drawn as a box labelled with its id, the focused one marked
+//! with a `*`, and the hidden clients are listed below the screen. `--focus` takes one
+//! of those ids, or `none`.
+use penrose::{
+    core::{
+        client::Client,
+        data_types::{Region, ResizeAction, WinId},
+        layout::Layout,
+        ring::Direction,
+        workspace::Workspace,
+    },
+    PenroseError,
+};
+use serde::Serialize;
+
+/// What to preview, as given on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preview {
+    /// Symbol of the layout, with or without the brackets
+    pub layout: String,
+    pub clients: usize,
+    pub screen: Region,
+    /// Id of the focused client, as labelled in the drawing, if any
+    pub focus: Option<usize>,
+    /// Width of the drawing in characters
+    pub columns: usize,
+}
+
+impl Preview {
+    /// Parse the arguments that follow `--preview-layout`.
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> penrose::Result<Self> {
+        let err = |e: String| PenroseError::Raw(e);
+        let mut preview = Preview {
+            layout: args
+                .next()
+                .ok_or_else(|| err("missing layout symbol".into()))?,
+            clients: 3,
+            screen: Region::new(0, 0, 1920, 1080),
+            focus: Some(1),
+            columns: 80,
+        };
+
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| err(format!("missing value for {}", flag)))?;
+            let number = |s: &str| {
+                s.parse::<u32>()
+                    .map_err(|_| err(format!("invalid value for {}: {}", flag, s)))
+            };
+            match flag.as_str() {
+                "--clients" => preview.clients = number(&value)? as usize,
+                "--columns" => preview.columns = number(&value)?.max(2) as usize,
+                "--focus" if value == "none" => preview.focus = None,
+                "--focus" if value == "0" => return Err(err("clients are numbered from 1".into())),
+                "--focus" => preview.focus = Some(number(&value)? as usize),
+                "--size" => {
+                    let (w, h) = value
+                        .split_once('x')
+                        .ok_or_else(|| err(format!("size should be WxH, got {}", value)))?;
+                    preview.screen = Region::new(0, 0, number(w)?, number(h)?);
+                }
+                _ => return Err(err(format!("unknown option {}", flag))),
+            }
+        }
+
+        Ok(preview)
+    }
+
+    /// Arrange fake clients with the matching layout out of `layouts` and draw them.
+    pub fn render(&self, layouts: Vec<Layout>) -> penrose::Result<String> {
+        let mut ws = Workspace::new("preview", layouts);
+        let symbols = symbols(&mut ws);
+        let symbol = symbols
+            .iter()
+            .find(|s| **s == self.layout || s.trim_matches(&['[', ']'][..]) == self.layout)
+            .ok_or_else(|| {
+                PenroseError::Raw(format!(
+                    "unknown layout {}, try one of {}",
+                    self.layout,
+                    symbols.join(" ")
+                ))
+            })?;
+        let layout = ws.try_set_layout(symbol).expect("symbol was just listed");
+
+        let clients = fake_clients(self.clients)?;
+        let refs: Vec<&Client> = clients.iter().collect();
+        let focused = self
+            .focus
+            .and_then(|id| clients.get(id.checked_sub(1)?))
+            .map(|c| c.id());
+        let actions = layout.arrange(&refs, focused, &self.screen);
+
+        Ok(format!(
+            "{} with {} clients on {}x{}\n{}",
+            symbol,
+            self.clients,
+            self.screen.w,
+            self.screen.h,
+            draw(&actions, focused, &self.screen, self.columns)
+        ))
+    }
+}
+
+fn symbols(ws: &mut Workspace) -> Vec<String> {
+    let first = ws.layout_symbol().to_string();
+    let mut symbols = vec![first.clone()];
+    while ws.cycle_layout(Direction::Forward) != first {
+        symbols.push(ws.layout_symbol().to_string());
+    }
+    symbols
+}
+
+// penrose only creates clients itself, but they can be deserialized
+#[derive(Serialize)]
+struct FakeClient {
+    id: WinId,
+    wm_name: String,
+    wm_class: String,
+    workspace: usize,
+    floating: bool,
+    fullscreen: bool,
+    mapped: bool,
+    wm_managed: bool,
+}
+
+/// `n` tiled clients on the first workspace, with ids from 1.
+pub fn fake_clients(n: usize) -> penrose::Result<Vec<Client>> {
+    (1..=n as WinId)
+        .map(|id| {
+            let fake = FakeClient {
+                id,
+                wm_name: format!("client {}", id),
+                wm_class: "preview".into(),
+                workspace: 0,
+                floating: false,
+                fullscreen: false,
+                mapped: true,
+                wm_managed: true,
+            };
+            let err = |e: String| PenroseError::Raw(e);
+            toml::Value::try_from(fake)
+                .map_err(|e| err(e.to_string()))?
+                .try_into()
+                .map_err(|e| err(e.to_string()))
+        })
+        .collect()
+}
+
+/**
+ * Draw `actions` on a grid `columns` characters wide, keeping the proportions of
+ * `screen` with characters twice as tall as they are wide. Boxes too small to have
+ * borders are filled with `#`.
+ */
+pub fn draw(
+    actions: &[ResizeAction],
+    focused: Option<WinId>,
+    screen: &Region,
+    columns: usize,
+) -> String {
+    let columns = columns.max(1);
+    let (sw, sh) = (screen.w.max(1) as usize, screen.h.max(1) as usize);
+    let rows = ((columns * sh) / (sw * 2)).max(1);
+    let mut grid = vec![vec![' '; columns]; rows];
+    let scale = |v: u32, from: u32, cells: usize, len: usize| {
+        ((v.saturating_sub(from) as usize * cells + len / 2) / len).min(cells)
+    };
+    let col = |x: u32| scale(x, screen.x, columns, sw);
+    let row = |y: u32| scale(y, screen.y, rows, sh);
+
+    let mut hidden = vec![];
+    for (id, region) in actions {
+        let r = match region {
+            Some(r) => r,
+            None => {
+                hidden.push(id.to_string());
+                continue;
+            }
+        };
+        if r.w == 0 || r.h == 0 {
+            continue;
+        }
+        // every visible client gets at least one cell
+        let (x0, y0) = (col(r.x).min(columns - 1), row(r.y).min(rows - 1));
+        let x1 = col(r.x + r.w).max(x0 + 1) - 1;
+        let y1 = row(r.y + r.h).max(y0 + 1) - 1;
+
+        for (y, line) in grid.iter_mut().enumerate().take(y1 + 1).skip(y0) {
+            for (x, c) in line.iter_mut().enumerate().take(x1 + 1).skip(x0) {
+                let (edge_x, edge_y) = (x == x0 || x == x1, y == y0 || y == y1);
+                *c = match (edge_x, edge_y) {
+                    _ if x1 == x0 || y1 == y0 => '#',
+                    (true, true) => '+',
+                    (false, true) => '-',
+                    (true, false) => '|',
+                    (false, false) => ' ',
+                };
+            }
+        }
+
+        let star = if Some(*id) == focused { "*" } else { "" };
+        let label = format!("{}{}", id, star);
+        if y1 > y0 + 1 && x1 > x0 + label.len() {
+            let line = &mut grid[y0 + 1];
+            line[x0 + 1..x0 + 1 + label.len()]
+                .iter_mut()
+                .zip(label.chars())
+                .for_each(|(c, l)| *c = l);
+        }
+    }
+
+    let mut s: String = grid
+        .into_iter()
+        .map(|line| line.into_iter().collect::<String>().trim_end().to_string() + "\n")
+        .collect();
+    if !hidden.is_empty() {
+        s += &format!("hidden: {}\n", hidden.join(", "));
+    }
+    s
+}
@@ -0,0 +1,795 @@
+//! Rules applied to new windows, built in code or read from a TOML file, e.g.
+//!
+//! ```toml
+//! [[rule]]
+//! class = "firefox"
+//! role = "PictureInPicture"
+//! geometry = [1420, 780, 480, 270]
+//! skip_focus = true
+//!
+//! [[rule]]
+//! title = "^Slack \\|"
+//! workspace = 8
+//!
+//! [[rule]]
+//! class = "Pinentry"
+//! size = [480, 240]
+//! placement = "pointer"
+//! ```
+//!
+//! Every rule whose conditions all hold is applied, in order, so later rules win. The
+//! [dialog_rules] float dialogs and the windows transient for another one.
+use log::error;
+use penrose::{
+    core::{
+        client::Client,
+        data_types::{Point, Region, WinId},
+        hooks::Hook,
+        manager::WindowManager,
+        ring::{InsertPoint, Selector},
+        xconnection::XConn,
+    },
+    PenroseError, Result,
+};
+use regex::Regex;
+
+use crate::floating::{
+    center_on, float_region, transient_for, FloatSize, Geometries, Placement, SizeHints,
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Float,
+    Tile,
+    /// Send the window to the workspace with this index
+    Workspace(usize),
+    /// Send the window to the workspace shown on the screen with this index
+    Screen(usize),
+    /// Place the window, relative to its screen
+    Geometry(Region),
+    /// Center the window on its screen, at the given size
+    Center(FloatSize),
+    /// Put the window somewhere else than the middle of the screen showing its workspace
+    Place(Placement),
+    Fullscreen,
+    /// Keep the focus where it was
+    SkipFocus,
+}
+
+/// The properties of a window rules match on.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WindowProps {
+    /// Second string of `WM_CLASS`
+    pub class: String,
+    /// First string of `WM_CLASS`
+    pub instance: String,
+    pub title: String,
+    /// `WM_WINDOW_ROLE`
+    pub role: String,
+    /// `_NET_WM_WINDOW_TYPE` without its prefix and in lowercase, e.g. `dialog`
+    pub window_type: String,
+    /// Whether `WM_TRANSIENT_FOR` names a managed window, only known to [Rules]
+    pub transient: bool,
+}
+
+impl WindowProps {
+    /// Read the properties of `c`, telling its type by the atoms of [window_types].
+    pub fn of<X: XConn>(wm: &WindowManager<X>, c: &Client, types: &[(u32, &str)]) -> Self {
+        let conn = wm.conn();
+        let wm_class = conn.str_prop(c.id(), "WM_CLASS").unwrap_or_default();
+        let mut wm_class = wm_class.split('\0');
+        let instance = wm_class.next().unwrap_or_default().to_string();
+        let class = wm_class.next().unwrap_or_default().to_string();
+
+        let window_type = conn
+            .atom_prop(c.id(), "_NET_WM_WINDOW_TYPE")
+            .ok()
+            .and_then(|atom| types.iter().find(|(a, _)| *a == atom))
+            .map_or("normal", |(_, t)| t)
+            .to_string();
+
+        Self {
+            class,
+            instance,
+            title: c.wm_name().to_string(),
+            role: conn.str_prop(c.id(), "WM_WINDOW_ROLE").unwrap_or_default(),
+            window_type,
+            transient: false,
+        }
+    }
+}
+
+const WINDOW_TYPES: [&str; 14] = [
+    "desktop",
+    "dock",
+    "toolbar",
+    "menu",
+    "utility",
+    "splash",
+    "dialog",
+    "dropdown_menu",
+    "popup_menu",
+    "tooltip",
+    "notification",
+    "combo",
+    "dnd",
+    "normal",
+];
+
+/// The `_NET_WM_WINDOW_TYPE_*` atoms, with the name of the type each one stands for.
+pub fn window_types<X: XConn>(wm: &WindowManager<X>) -> Vec<(u32, &'static str)> {
+    WINDOW_TYPES
+        .iter()
+        .filter_map(|t| {
+            let name = format!("_NET_WM_WINDOW_TYPE_{}", t.to_uppercase());
+            Some((wm.conn().intern_atom(&name).ok()?, *t))
+        })
+        .collect()
+}
+
+/// A set of conditions on new windows and the actions for the ones meeting them.
+#[derive(Debug, Default, Clone)]
+pub struct Rule {
+    /// Any of these classes, if not empty
+    classes: Vec<String>,
+    /// Any of these instances, if not empty
+    instances: Vec<String>,
+    title: Option<Regex>,
+    role: Option<String>,
+    window_type: Option<String>,
+    transient: Option<bool>,
+    actions: Vec<Action>,
+}
+
+impl Rule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn class(mut self, class: impl Into<String>) -> Self {
+        self.classes.push(class.into());
+        self
+    }
+
+    pub fn instance(mut self, instance: impl Into<String>) -> Self {
+        self.instances.push(instance.into());
+        self
+    }
+
+    pub fn instances(mut self, instances: &[impl AsRef<str>]) -> Self {
+        self.instances
+            .extend(instances.iter().map(|i| i.as_ref().to_string()));
+        self
+    }
+
+    /// Match the title against the regular expression `pattern`.
+    pub fn title(mut self, pattern: &str) -> Result<Self> {
+        self.title = Some(Regex::new(pattern).map_err(|e| PenroseError::Raw(e.to_string()))?);
+        Ok(self)
+    }
+
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.role = Some(role.into());
+        self
+    }
+
+    /// Match `_NET_WM_WINDOW_TYPE`, as in `dialog` for `_NET_WM_WINDOW_TYPE_DIALOG`.
+    pub fn window_type(mut self, window_type: impl Into<String>) -> Self {
+        self.window_type = Some(window_type.into().to_lowercase());
+        self
+    }
+
+    /// Match windows that are, or aren't, transient for a managed window.
+    pub fn transient(mut self, transient: bool) -> Self {
+        self.transient = Some(transient);
+        self
+    }
+
+    pub fn then(mut self, action: Action) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    pub fn float(self) -> Self {
+        self.then(Action::Float)
+    }
+
+    pub fn tile(self) -> Self {
+        self.then(Action::Tile)
+    }
+
+    pub fn workspace(self, index: usize) -> Self {
+        self.then(Action::Workspace(index))
+    }
+
+    pub fn screen(self, index: usize) -> Self {
+        self.then(Action::Screen(index))
+    }
+
+    /// Float the window at `region`, relative to its screen.
+    pub fn geometry(self, region: Region) -> Self {
+        self.float().then(Action::Geometry(region))
+    }
+
+    /// Float the window in the middle of its screen, sized by `size`.
+    pub fn center(self, size: FloatSize) -> Self {
+        self.float().then(Action::Center(size))
+    }
+
+    /// Float the window where `placement` says, keeping its size unless it is centered.
+    pub fn place(self, placement: Placement) -> Self {
+        self.float().then(Action::Place(placement))
+    }
+
+    pub fn fullscreen(self) -> Self {
+        self.then(Action::Fullscreen)
+    }
+
+    pub fn skip_focus(self) -> Self {
+        self.then(Action::SkipFocus)
+    }
+
+    pub fn matches(&self, props: &WindowProps) -> bool {
+        let any =
+            |wanted: &[String], value: &str| wanted.is_empty() || wanted.iter().any(|w| w == value);
+        any(&self.classes, &props.class)
+            && any(&self.instances, &props.instance)
+            && self.title.as_ref().is_none_or(|t| t.is_match(&props.title))
+            && self.role.as_ref().is_none_or(|r| *r == props.role)
+            && self
+                .window_type
+                .as_ref()
+                .is_none_or(|t| *t == props.window_type)
+            && self.transient.is_none_or(|t| t == props.transient)
+    }
+}
+
+/// Window types that always float, even without a window they belong to
+const FLOATING_TYPES: [&str; 3] = ["dialog", "splash", "utility"];
+
+/**
+ * Float windows transient for another one over their parent, and dialogs, splash screens
+ * and utility windows without one in the middle of their screen. [Rules] keeps the former
+ * above their parent and sends them wherever it goes.
+ */
+pub fn dialog_rules() -> Vec<Rule> {
+    let mut rules = vec![Rule::new().transient(true).place(Placement::Parent)];
+    rules.extend(FLOATING_TYPES.iter().map(|t| {
+        Rule::new()
+            .transient(false)
+            .window_type(*t)
+            .place(Placement::Screen)
+    }));
+    rules
+}
+
+/// The actions of every rule matching `props`, in the order of the rules.
+pub fn actions_for(rules: &[Rule], props: &WindowProps) -> Vec<Action> {
+    rules
+        .iter()
+        .filter(|r| r.matches(props))
+        .flat_map(|r| r.actions.iter().copied())
+        .collect()
+}
+
+/// A [Rule] in a rules file, its actions are applied in the order of the fields.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDef {
+    class: Option<String>,
+    instance: Option<String>,
+    title: Option<String>,
+    role: Option<String>,
+    window_type: Option<String>,
+    transient: Option<bool>,
+    #[serde(default)]
+    float: bool,
+    #[serde(default)]
+    tile: bool,
+    workspace: Option<usize>,
+    screen: Option<usize>,
+    /// `[x, y, w, h]`
+    geometry: Option<[u32; 4]>,
+    /// Center the window taking this fraction of the screen
+    center: Option<f64>,
+    /// Center the window at `[w, h]`
+    size: Option<[u32; 2]>,
+    /// Prefer the size the window asks for
+    #[serde(default)]
+    size_hints: bool,
+    min_size: Option<[u32; 2]>,
+    max_size: Option<[u32; 2]>,
+    #[serde(default)]
+    keep_aspect: bool,
+    /// `screen`, `pointer_screen`, `parent` or `pointer`
+    placement: Option<Placement>,
+    #[serde(default)]
+    fullscreen: bool,
+    #[serde(default)]
+    skip_focus: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<RuleDef>,
+}
+
+impl RuleDef {
+    fn into_rule(self) -> Result<Rule> {
+        let mut rule = Rule::new();
+        if let Some(class) = self.class {
+            rule = rule.class(class);
+        }
+        if let Some(instance) = self.instance {
+            rule = rule.instance(instance);
+        }
+        if let Some(title) = self.title {
+            rule = rule.title(&title)?;
+        }
+        if let Some(role) = self.role {
+            rule = rule.role(role);
+        }
+        if let Some(window_type) = self.window_type {
+            rule = rule.window_type(window_type);
+        }
+        if let Some(transient) = self.transient {
+            rule = rule.transient(transient);
+        }
+        if self.float {
+            rule = rule.float();
+        }
+        if self.tile {
+            rule = rule.tile();
+        }
+        if let Some(index) = self.workspace {
+            rule = rule.workspace(index);
+        }
+        if let Some(index) = self.screen {
+            rule = rule.screen(index);
+        }
+        if let Some([x, y, w, h]) = self.geometry {
+            rule = rule.geometry(Region::new(x, y, w, h));
+        }
+        let size = match (self.size, self.center) {
+            (Some([w, h]), _) => Some(FloatSize::absolute(w, h)),
+            (None, Some(scale)) => Some(FloatSize::scale(scale)),
+            (None, None) => None,
+        };
+        match size {
+            Some(mut size) => {
+                if self.size_hints {
+                    size = size.with_hints();
+                }
+                if let Some([w, h]) = self.min_size {
+                    size = size.min(w, h);
+                }
+                if let Some([w, h]) = self.max_size {
+                    size = size.max(w, h);
+                }
+                if self.keep_aspect {
+                    size = size.keep_aspect();
+                }
+                rule = rule.center(size);
+            }
+            None if self.size_hints
+                || self.min_size.is_some()
+                || self.max_size.is_some()
+                || self.keep_aspect =>
+            {
+                return Err(PenroseError::Raw(
+                    "sizing a window needs either center or size".into(),
+                ));
+            }
+            None => (),
+        }
+        if let Some(placement) = self.placement {
+            rule = rule.place(placement);
+        }
+        if self.fullscreen {
+            rule = rule.fullscreen();
+        }
+        if self.skip_focus {
+            rule = rule.skip_focus();
+        }
+        Ok(rule)
+    }
+}
+
+/// Parse the `[[rule]]` entries of a rules file.
+pub fn parse_rules(s: &str) -> Result<Vec<Rule>> {
+    let file: RulesFile = toml::from_str(s).map_err(|e| PenroseError::Raw(e.to_string()))?;
+    file.rule.into_iter().map(|r| r.into_rule()).collect()
+}
+
+/// Load the rules in the file at `path`, if there is one.
+pub fn load_rules(path: impl AsRef<Path>) -> Result<Vec<Rule>> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let s = fs::read_to_string(path).map_err(|e| PenroseError::Raw(e.to_string()))?;
+    parse_rules(&s)
+}
+
+// penrose doesn't let hooks change whether a client floats, but clients can be rebuilt
+pub fn set_floating(c: &mut Client, floating: bool) -> Result<()> {
+    let err = |e: String| PenroseError::Raw(e);
+    let mut value = toml::Value::try_from(&*c).map_err(|e| err(e.to_string()))?;
+    if let Some(table) = value.as_table_mut() {
+        table.insert("floating".into(), toml::Value::Boolean(floating));
+    }
+    *c = value.try_into().map_err(|e| err(e.to_string()))?;
+    Ok(())
+}
+
+/// Actions that need the window to be managed already.
+struct Pending {
+    id: WinId,
+    actions: Vec<Action>,
+    focused: Option<WinId>,
+    /// Class, size hints and size of the window when it was created, for centering it
+    class: String,
+    hints: SizeHints,
+    size: Option<(u32, u32)>,
+    placement: Placement,
+    /// Where the pointer was when the window was created
+    pointer: Point,
+    /// The managed window it is transient for
+    parent: Option<WinId>,
+}
+
+/**
+ * Applies the matching [Rule]s to every new window. Centered windows that are moved or
+ * resized keep their geometry for the next window of their class, saved to `state_file`.
+ */
+pub struct Rules {
+    rules: Vec<Rule>,
+    pending: Vec<Pending>,
+    /// For the properties penrose has no way to read and for following centered windows,
+    /// connected when first needed
+    conn: Option<xcb::Connection>,
+    geometries: Geometries,
+    /// The window every window placed over its parent belongs to
+    parents: HashMap<WinId, WinId>,
+    /// See [window_types], interned for the first window
+    window_types: Vec<(u32, &'static str)>,
+}
+
+impl Rules {
+    pub fn new(rules: Vec<Rule>, state_file: impl Into<PathBuf>) -> Box<Self> {
+        Box::new(Self {
+            rules,
+            pending: vec![],
+            conn: None,
+            geometries: Geometries::new(state_file),
+            parents: HashMap::new(),
+            window_types: vec![],
+        })
+    }
+
+    fn conn(&mut self) -> Option<&xcb::Connection> {
+        if self.conn.is_none() {
+            self.conn = xcb::Connection::connect(None).ok().map(|(conn, _)| conn);
+        }
+        self.conn.as_ref()
+    }
+
+    fn size_hints(&mut self, id: WinId) -> SizeHints {
+        self.conn()
+            .map(|conn| SizeHints::of(conn, id))
+            .unwrap_or_default()
+    }
+
+    /// Get told on our own connection when `id` is moved or resized.
+    fn watch(&mut self, id: WinId) {
+        if let Some(conn) = self.conn() {
+            xcb::change_window_attributes(
+                conn,
+                id,
+                &[(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_STRUCTURE_NOTIFY)],
+            );
+            conn.flush();
+        }
+    }
+
+    /// Where the watched windows were configured since the last call, in order.
+    fn configured(&self) -> Vec<(WinId, Region)> {
+        let conn = match &self.conn {
+            Some(conn) => conn,
+            None => return vec![],
+        };
+        let mut configured = vec![];
+        while let Some(event) = conn.poll_for_event() {
+            if event.response_type() & !0x80 != xcb::CONFIGURE_NOTIFY {
+                continue;
+            }
+            // SAFETY: the response type was just checked
+            let e: &xcb::ConfigureNotifyEvent = unsafe { xcb::cast_event(&event) };
+            let (x, y) = (e.x().max(0) as u32, e.y().max(0) as u32);
+            configured.push((
+                e.window(),
+                Region::new(x, y, e.width() as u32, e.height() as u32),
+            ));
+        }
+        configured
+    }
+
+    /// The managed window `id` is transient for.
+    fn parent<X: XConn>(&mut self, wm: &WindowManager<X>, id: WinId) -> Option<WinId> {
+        let parent = transient_for(self.conn()?, id)?;
+        wm.client(&Selector::WinId(parent)).map(|_| parent)
+    }
+
+    /// Send the windows placed over `parent` to the workspace `wix` it was just added to,
+    /// without focusing them as penrose would.
+    fn follow<X: XConn>(&self, wm: &mut WindowManager<X>, parent: WinId, wix: usize) -> Result<()> {
+        let children: Vec<WinId> = self
+            .parents
+            .iter()
+            .filter(|(_, p)| **p == parent)
+            .map(|(id, _)| *id)
+            .collect();
+        let shown = wm.focused_workspaces().contains(&wix);
+
+        for id in children {
+            let from = match wm.client(&Selector::WinId(id)) {
+                Some(c) if c.workspace() != wix => c.workspace(),
+                _ => continue,
+            };
+            if let Some(ws) = wm.workspace_mut(&Selector::Index(from)) {
+                ws.remove_client(id);
+            }
+            if let Some(ws) = wm.workspace_mut(&Selector::Index(wix)) {
+                ws.add_client(id, &InsertPoint::Last)?;
+            }
+            if let Some(c) = wm.client_mut(&Selector::WinId(id)) {
+                c.set_workspace(wix);
+            }
+            if shown {
+                wm.show_client(id)?;
+            } else {
+                wm.hide_client(id)?;
+            }
+            wm.conn().set_client_workspace(id, wix);
+            self.follow(wm, id, wix)?;
+        }
+        Ok(())
+    }
+
+    /// Put the windows placed over their parent on workspace `wix` back above the others.
+    fn raise<X: XConn>(&self, wm: &WindowManager<X>, wix: usize) {
+        self.parents
+            .keys()
+            .filter(|id| {
+                wm.client(&Selector::WinId(**id))
+                    .is_some_and(|c| c.workspace() == wix)
+            })
+            .for_each(|id| wm.conn().raise_window(*id));
+    }
+
+    fn apply_pending<X: XConn>(&mut self, wm: &mut WindowManager<X>, p: &Pending) -> Result<()> {
+        let (screen, screen_region) = match screen_of(wm, p.id) {
+            Some((screen, region)) => (screen, Some(region)),
+            None => return Ok(()),
+        };
+        // the point to center on, when it isn't the middle of the screen
+        let anchor = match p.placement {
+            Placement::Screen | Placement::PointerScreen => None,
+            Placement::Parent => p
+                .parent
+                .and_then(|id| wm.conn().window_geometry(id).ok())
+                .map(|r| Point::new(r.x + r.w / 2, r.y + r.h / 2)),
+            Placement::Pointer => Some(p.pointer),
+        };
+        let centered = p.actions.iter().any(|a| matches!(a, Action::Center(_)));
+
+        for action in p.actions.iter() {
+            match (action, screen_region) {
+                (Action::Geometry(r), Some(s)) => {
+                    let r = Region::new(s.x + r.x, s.y + r.y, r.w, r.h);
+                    wm.position_client(p.id, r, true)?;
+                }
+                (Action::Center(size), Some(s)) => {
+                    let mut r = self
+                        .geometries
+                        .get(&p.class, &s)
+                        .unwrap_or_else(|| float_region(size, &p.hints, p.size, &s));
+                    if let Some(point) = anchor {
+                        r = center_on(r, point, &s);
+                    }
+                    self.geometries.place(p.id, &p.class, r);
+                    self.watch(p.id);
+                    wm.position_client(p.id, r, true)?;
+                }
+                (Action::Place(_), Some(s)) if !centered => {
+                    let (w, h) = p.size.unwrap_or((s.w / 2, s.h / 2));
+                    let middle = Point::new(s.x + s.w / 2, s.y + s.h / 2);
+                    let r = center_on(Region::new(0, 0, w, h), anchor.unwrap_or(middle), &s);
+                    wm.position_client(p.id, r, true)?;
+                }
+                (Action::Fullscreen, _)
+                    if wm
+                        .client(&Selector::WinId(p.id))
+                        .is_some_and(|c| !c.is_fullscreen()) =>
+                {
+                    wm.toggle_client_fullscreen(&Selector::WinId(p.id))?;
+                }
+                (Action::SkipFocus, _) => {
+                    if let Some(id) = p.focused {
+                        let _ = wm.focus_client(&Selector::WinId(id));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // windows sent to a workspace on another screen are only shown once it is laid out
+        if screen != wm.active_screen_index() {
+            wm.layout_screen(screen)?;
+        }
+        Ok(())
+    }
+}
+
+/// The screen under `point`.
+fn screen_at<X: XConn>(wm: &WindowManager<X>, point: &Point) -> Option<usize> {
+    (0..wm.n_screens()).find(|i| wm.screen_size(*i).is_some_and(|r| r.contains_point(point)))
+}
+
+/// The screen showing the workspace of `id`, or the active one if it is hidden.
+pub fn screen_of<X: XConn>(wm: &WindowManager<X>, id: WinId) -> Option<(usize, Region)> {
+    let wix = wm.client(&Selector::WinId(id))?.workspace();
+    let screen = wm
+        .focused_workspaces()
+        .iter()
+        .position(|w| *w == wix)
+        .unwrap_or_else(|| wm.active_screen_index());
+    Some((screen, wm.screen_size(screen)?))
+}
+
+impl<X: XConn> Hook<X> for Rules {
+    fn startup(&mut self, _: &mut WindowManager<X>) -> Result<()> {
+        self.geometries.load()
+    }
+
+    fn new_client(&mut self, wm: &mut WindowManager<X>, c: &mut Client) -> Result<()> {
+        if self.window_types.is_empty() {
+            self.window_types = window_types(wm);
+        }
+        let transient_parent = self.parent(wm, c.id());
+        let props = WindowProps {
+            transient: transient_parent.is_some(),
+            ..WindowProps::of(wm, c, &self.window_types)
+        };
+        let actions = actions_for(&self.rules, &props);
+        if actions.is_empty() {
+            return Ok(());
+        }
+
+        let pointer = wm.conn().cursor_position();
+        let mut parent = None;
+        let mut placement = Placement::default();
+        let mut deferred = vec![];
+        for action in actions {
+            match action {
+                Action::Float => set_floating(c, true)?,
+                Action::Tile => set_floating(c, false)?,
+                Action::Workspace(wix) => c.set_workspace(wix),
+                Action::Screen(screen) => {
+                    if let Some(wix) = wm.focused_workspaces().get(screen) {
+                        c.set_workspace(*wix);
+                    }
+                }
+                Action::Place(p) => {
+                    placement = p;
+                    let wix = match p {
+                        Placement::Screen => None,
+                        Placement::Parent => {
+                            parent = transient_parent;
+                            parent
+                                .and_then(|id| wm.client(&Selector::WinId(id)))
+                                .map(|c| c.workspace())
+                        }
+                        Placement::PointerScreen | Placement::Pointer => screen_at(wm, &pointer)
+                            .and_then(|screen| wm.focused_workspaces().get(screen).copied()),
+                    };
+                    if let Some(wix) = wix {
+                        c.set_workspace(wix);
+                    }
+                    deferred.push(action);
+                }
+                _ => deferred.push(action),
+            }
+        }
+        if !deferred.is_empty() {
+            let centered = deferred.iter().any(|a| matches!(a, Action::Center(_)));
+            let placed = deferred.iter().any(|a| matches!(a, Action::Place(_)));
+            let size = if centered || placed {
+                wm.conn().window_geometry(c.id()).ok().map(|r| (r.w, r.h))
+            } else {
+                None
+            };
+            let hints = if centered {
+                self.size_hints(c.id())
+            } else {
+                SizeHints::default()
+            };
+            let class = match props.class.as_str() {
+                "" => props.instance,
+                class => class.to_string(),
+            };
+            self.pending.push(Pending {
+                id: c.id(),
+                actions: deferred,
+                focused: wm.focused_client_id(),
+                class,
+                hints,
+                size,
+                placement,
+                pointer,
+                parent,
+            });
+        }
+        if let Some(parent) = parent {
+            self.parents.insert(c.id(), parent);
+        }
+        Ok(())
+    }
+
+    fn remove_client(&mut self, _: &mut WindowManager<X>, id: WinId) -> Result<()> {
+        self.geometries.forget(id);
+        self.parents.remove(&id);
+        self.parents.retain(|_, parent| *parent != id);
+        Ok(())
+    }
+
+    fn client_added_to_workspace(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: WinId,
+        wix: usize,
+    ) -> Result<()> {
+        self.follow(wm, id, wix)
+    }
+
+    fn focus_change(&mut self, wm: &mut WindowManager<X>, id: WinId) -> Result<()> {
+        if let Some(wix) = wm.client(&Selector::WinId(id)).map(|c| c.workspace()) {
+            self.raise(wm, wix);
+        }
+        Ok(())
+    }
+
+    fn layout_applied(&mut self, wm: &mut WindowManager<X>, wix: usize, _: usize) -> Result<()> {
+        self.raise(wm, wix);
+        Ok(())
+    }
+
+    // the new window is only managed once its map request has been handled, and moving
+    // a watched window wakes penrose up as well, so its configure events are read here
+    // without asking the server where every window is
+    fn event_handled(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+        let pending: Vec<Pending> = self.pending.drain(..).collect();
+        for p in pending.iter() {
+            if let Err(e) = self.apply_pending(wm, p) {
+                error!("rules: unable to apply the rules of {}: {}", p.id, e);
+            }
+        }
+
+        for (id, region) in self.configured() {
+            if let Some((_, screen)) = screen_of(wm, id) {
+                if let Err(e) = self.geometries.moved(id, &region, &screen) {
+                    error!("rules: unable to save where {} was moved: {}", id, e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
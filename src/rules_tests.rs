@@ -0,0 +1,142 @@
+//! Tests for matching windows against rules and the order their actions come in.
+use penrose::core::data_types::Region;
+
+use crate::{
+    floating::FloatSize,
+    floating::Placement,
+    rules::{actions_for, dialog_rules, parse_rules, Action, Rule, WindowProps},
+};
+
+fn firefox() -> WindowProps {
+    WindowProps {
+        class: "firefox".into(),
+        instance: "Navigator".into(),
+        title: "Picture-in-Picture".into(),
+        role: "PictureInPicture".into(),
+        window_type: "normal".into(),
+        transient: false,
+    }
+}
+
+#[test]
+fn rules_match_on_every_property() {
+    let props = firefox();
+    assert!(Rule::new().matches(&props));
+    assert!(Rule::new().class("firefox").matches(&props));
+    assert!(!Rule::new().class("Firefox").matches(&props));
+    assert!(Rule::new().instance("Navigator").matches(&props));
+    assert!(Rule::new()
+        .instances(&["Toolkit", "Navigator"])
+        .matches(&props));
+    assert!(!Rule::new().instances(&["Toolkit"]).matches(&props));
+    assert!(Rule::new().title("^Picture").unwrap().matches(&props));
+    assert!(Rule::new().title("in-Pic").unwrap().matches(&props));
+    assert!(!Rule::new().title("^in").unwrap().matches(&props));
+    assert!(Rule::new().role("PictureInPicture").matches(&props));
+    assert!(!Rule::new().role("browser").matches(&props));
+    assert!(Rule::new().window_type("NORMAL").matches(&props));
+    assert!(!Rule::new().window_type("dialog").matches(&props));
+    assert!(Rule::new().transient(false).matches(&props));
+    assert!(!Rule::new().transient(true).matches(&props));
+}
+
+#[test]
+fn rules_match_only_when_all_their_conditions_hold() {
+    let props = firefox();
+    let rule = || Rule::new().class("firefox").role("PictureInPicture");
+    assert!(rule().matches(&props));
+    assert!(!rule().instance("Toolkit").matches(&props));
+    assert!(!rule().title("Mozilla").unwrap().matches(&props));
+    assert!(
+        rule().class("Navigator").matches(&props),
+        "classes are alternatives"
+    );
+}
+
+#[test]
+fn actions_of_matching_rules_are_applied_in_order() {
+    let rules = vec![
+        Rule::new().class("firefox").float().workspace(1),
+        Rule::new().class("chromium").tile(),
+        Rule::new()
+            .title("Picture")
+            .unwrap()
+            .workspace(3)
+            .skip_focus(),
+    ];
+    assert_eq!(
+        actions_for(&rules, &firefox()),
+        vec![
+            Action::Float,
+            Action::Workspace(1),
+            Action::Workspace(3),
+            Action::SkipFocus
+        ]
+    );
+    assert!(actions_for(&rules, &WindowProps::default()).is_empty());
+}
+
+#[test]
+fn declared_rules_keep_the_order_of_their_fields() {
+    let rules = parse_rules(
+        r#"
+        [[rule]]
+        class = "firefox"
+        skip_focus = true
+        geometry = [10, 20, 300, 200]
+        workspace = 2
+
+        [[rule]]
+        role = "PictureInPicture"
+        center = 0.5
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        actions_for(&rules, &firefox()),
+        vec![
+            Action::Workspace(2),
+            Action::Float,
+            Action::Geometry(Region::new(10, 20, 300, 200)),
+            Action::SkipFocus,
+            Action::Float,
+            Action::Center(FloatSize::scale(0.5)),
+        ]
+    );
+}
+
+#[test]
+fn dialogs_float_over_their_parent_or_their_screen() {
+    let window = |window_type: &str, transient: bool| WindowProps {
+        window_type: window_type.into(),
+        transient,
+        ..firefox()
+    };
+    let over = |placement| vec![Action::Float, Action::Place(placement)];
+    let rules = dialog_rules();
+
+    assert_eq!(
+        actions_for(&rules, &window("normal", true)),
+        over(Placement::Parent)
+    );
+    assert_eq!(
+        actions_for(&rules, &window("dialog", true)),
+        over(Placement::Parent)
+    );
+    for window_type in ["dialog", "splash", "utility"] {
+        assert_eq!(
+            actions_for(&rules, &window(window_type, false)),
+            over(Placement::Screen)
+        );
+    }
+    assert!(actions_for(&rules, &window("normal", false)).is_empty());
+    assert!(actions_for(&rules, &window("toolbar", false)).is_empty());
+
+    // later rules still win
+    let mut rules = rules;
+    rules.extend(parse_rules("[[rule]]\ntransient = true\ntile = true").unwrap());
+    assert_eq!(
+        actions_for(&rules, &window("normal", true)).last(),
+        Some(&Action::Tile)
+    );
+}
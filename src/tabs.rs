@@ -0,0 +1,281 @@
+use penrose::{
+    core::{
+        data_types::{PropVal, Region, WinId, WinType},
+        hooks::Hook,
+        manager::WindowManager,
+        ring::Selector,
+        xconnection::{Atom, XConn},
+    },
+    draw::{Color, Draw, DrawContext, TextStyle},
+    PenroseError,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::layouts::{set_tab_height, take_tab_strip, TabStrip};
+
+const TAB_BAR_NAME: &str = "penrose_tabs";
+const CLICK_MESSAGE: &str = "_PENROSE_TAB_CLICK";
+
+/// Shorten `txt` with an ellipsis until it fits in `width`, along with its extent.
+fn fit_text(
+    ctx: &dyn DrawContext,
+    txt: &str,
+    width: f64,
+) -> penrose::draw::Result<Option<(String, (f64, f64))>> {
+    let mut chars: Vec<char> = txt.chars().collect();
+    let mut fitted = txt.to_string();
+    loop {
+        let extent = ctx.text_extent(&fitted)?;
+        if extent.0 <= width {
+            return Ok(Some((fitted, extent)));
+        }
+        if chars.pop().is_none() {
+            return Ok(None);
+        }
+        fitted = format!("{}…", chars.iter().collect::<String>());
+    }
+}
+
+/**
+ * Draws the tab strip reserved by the `tabbed` layout: one tab per client with its title,
+ * highlighting the focused one. Clicking on a tab focuses its client.
+ *
+ * Clicks are read from a connection of our own in a separate thread, which then pokes the
+ * root window with a client message so that penrose wakes up and runs `event_handled`.
+ */
+pub struct TabBar<C, D>
+where
+    C: DrawContext,
+    D: Draw<Ctx = C>,
+{
+    drw: D,
+    conn: Arc<xcb::Connection>,
+    clicks: Arc<Mutex<Vec<(WinId, i16)>>>,
+    style: TextStyle,
+    highlight: Color,
+    screens: HashMap<usize, (WinId, TabStrip)>,
+}
+
+impl<C, D> TabBar<C, D>
+where
+    C: DrawContext,
+    D: Draw<Ctx = C>,
+{
+    pub fn new(
+        mut drw: D,
+        height: usize,
+        style: &TextStyle,
+        highlight: impl Into<Color>,
+    ) -> penrose::Result<Self> {
+        set_tab_height(height as u32);
+        drw.register_font(&style.font);
+
+        let (conn, screen_i) =
+            xcb::Connection::connect(None).map_err(|e| PenroseError::Raw(e.to_string()))?;
+        let root = conn
+            .get_setup()
+            .roots()
+            .nth(screen_i as usize)
+            .map(|screen| screen.root())
+            .ok_or_else(|| PenroseError::Raw("no root window".into()))?;
+        let message = xcb::intern_atom(&conn, false, CLICK_MESSAGE)
+            .get_reply()
+            .map_err(|e| PenroseError::Raw(format!("{:?}", e)))?
+            .atom();
+
+        let conn = Arc::new(conn);
+        let clicks = Arc::new(Mutex::new(vec![]));
+        {
+            let conn = Arc::clone(&conn);
+            let clicks = Arc::clone(&clicks);
+            thread::spawn(move || {
+                while let Some(event) = conn.wait_for_event() {
+                    if event.response_type() & !0x80 != xcb::BUTTON_PRESS {
+                        continue;
+                    }
+                    let press: &xcb::ButtonPressEvent = unsafe { xcb::cast_event(&event) };
+                    if let Ok(mut clicks) = clicks.lock() {
+                        clicks.push((press.event(), press.event_x()));
+                    }
+                    let wake = xcb::ClientMessageEvent::new(
+                        32,
+                        root,
+                        message,
+                        xcb::ClientMessageData::from_data32([press.event(), 0, 0, 0, 0]),
+                    );
+                    xcb::send_event(
+                        &conn,
+                        false,
+                        root,
+                        xcb::EVENT_MASK_SUBSTRUCTURE_REDIRECT | xcb::EVENT_MASK_SUBSTRUCTURE_NOTIFY,
+                        &wake,
+                    );
+                    conn.flush();
+                }
+            });
+        }
+
+        Ok(Self {
+            drw,
+            conn,
+            clicks,
+            style: style.clone(),
+            highlight: highlight.into(),
+            screens: HashMap::new(),
+        })
+    }
+
+    fn create_window(&mut self, region: Region) -> penrose::Result<WinId> {
+        let id =
+            self.drw
+                .new_window(WinType::InputOutput(Atom::NetWindowTypeDock), region, false)?;
+        xcb::change_window_attributes(
+            &self.conn,
+            id,
+            &[(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_BUTTON_PRESS)],
+        );
+        self.conn.flush();
+
+        let s = PropVal::Str(TAB_BAR_NAME);
+        self.drw.replace_prop(id, Atom::NetWmName, s);
+        self.drw.replace_prop(id, Atom::WmName, s);
+        self.drw.replace_prop(id, Atom::WmClass, s);
+        self.drw.map_window(id);
+        Ok(id)
+    }
+
+    fn show<X: XConn>(
+        &mut self,
+        wm: &WindowManager<X>,
+        screen: usize,
+        strip: TabStrip,
+    ) -> penrose::Result<()> {
+        let id = match self.screens.remove(&screen) {
+            Some((id, old)) if old.region == strip.region => {
+                self.drw.map_window(id);
+                id
+            }
+            Some((id, _)) => {
+                self.drw.destroy_window(id);
+                self.create_window(strip.region)?
+            }
+            None => self.create_window(strip.region)?,
+        };
+        self.screens.insert(screen, (id, strip));
+        self.redraw(wm, screen)
+    }
+
+    fn hide(&mut self, screen: usize) {
+        if let Some((id, _)) = self.screens.get(&screen) {
+            self.drw.unmap_window(*id);
+            self.drw.flush(*id);
+        }
+    }
+
+    fn redraw<X: XConn>(&mut self, wm: &WindowManager<X>, screen: usize) -> penrose::Result<()> {
+        let (id, strip) = match self.screens.get(&screen) {
+            Some(s) => s,
+            None => return Ok(()),
+        };
+        let mut ctx = self.drw.context_for(*id)?;
+        let (w, h) = (strip.region.w as f64, strip.region.h as f64);
+        let tab_w = w / strip.clients.len().max(1) as f64;
+        let bg = self.style.bg.unwrap_or_else(|| 0x000000.into());
+        let (l, r) = self.style.padding;
+
+        ctx.clear();
+        ctx.font(&self.style.font, self.style.point_size)?;
+        for (i, client) in strip.clients.iter().enumerate() {
+            ctx.set_x_offset(i as f64 * tab_w);
+            ctx.color(if *client == strip.focused {
+                &self.highlight
+            } else {
+                &bg
+            });
+            ctx.rectangle(0.0, 0.0, tab_w, h);
+
+            let name = wm
+                .client(&Selector::WinId(*client))
+                .map(|c| c.wm_name().to_string())
+                .unwrap_or_default();
+            let (title, (_, text_h)) = match fit_text(&ctx, &name, tab_w - l - r)? {
+                Some(fitted) => fitted,
+                None => continue,
+            };
+            ctx.color(&self.style.fg);
+            ctx.text(&title, (h - text_h) / 2.0, (l, r))?;
+        }
+        ctx.flush();
+        self.drw.flush(*id);
+        Ok(())
+    }
+
+    fn tab_at(&self, window: WinId, x: i16) -> Option<WinId> {
+        self.screens
+            .values()
+            .find(|(id, _)| *id == window)
+            .and_then(|(_, strip)| {
+                let n = strip.clients.len().max(1) as u32;
+                let i = (x.max(0) as u32 * n / strip.region.w.max(1)) as usize;
+                strip.clients.get(i).copied()
+            })
+    }
+}
+
+impl<C, D, X> Hook<X> for TabBar<C, D>
+where
+    C: DrawContext,
+    D: Draw<Ctx = C>,
+    X: XConn,
+{
+    fn layout_applied(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        workspace_index: usize,
+        screen_index: usize,
+    ) -> penrose::Result<()> {
+        match take_tab_strip(workspace_index) {
+            Some(strip) => self.show(wm, screen_index, strip),
+            None => {
+                self.hide(screen_index);
+                Ok(())
+            }
+        }
+    }
+
+    fn client_name_updated(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: WinId,
+        _: &str,
+        _: bool,
+    ) -> penrose::Result<()> {
+        let screens: Vec<usize> = self
+            .screens
+            .iter()
+            .filter(|(_, (_, strip))| strip.clients.contains(&id))
+            .map(|(screen, _)| *screen)
+            .collect();
+        for screen in screens {
+            self.redraw(wm, screen)?;
+        }
+        Ok(())
+    }
+
+    fn event_handled(&mut self, wm: &mut WindowManager<X>) -> penrose::Result<()> {
+        let clicks: Vec<(WinId, i16)> = match self.clicks.lock() {
+            Ok(mut clicks) => clicks.drain(..).collect(),
+            Err(_) => return Ok(()),
+        };
+        for (window, x) in clicks {
+            if let Some(id) = self.tab_at(window, x) {
+                let _ = wm.focus_client(&Selector::WinId(id));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use penrose::core::{
+    client::Client,
+    data_types::{Region, ResizeAction, WinId},
+    layout::LayoutFunc,
+};
+use std::{cell::RefCell, collections::HashMap};
+
+/// How the regions of a layout are moved around before being applied.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// Swap left and right
+    pub mirror: bool,
+    /// Swap top and bottom
+    pub flip: bool,
+    /// Swap rows and columns
+    pub transpose: bool,
+}
+
+thread_local! {
+    static TRANSFORMS: RefCell<HashMap<usize, Transform>> = RefCell::new(HashMap::new());
+}
+
+fn update_transform<F: FnOnce(&mut Transform)>(workspace: usize, f: F) -> Transform {
+    TRANSFORMS.with(|t| {
+        let mut transforms = t.borrow_mut();
+        let transform = transforms.entry(workspace).or_default();
+        f(transform);
+        *transform
+    })
+}
+
+/// Swap left and right on `workspace`, returning its transform now.
+pub fn toggle_mirror(workspace: usize) -> Transform {
+    update_transform(workspace, |t| t.mirror = !t.mirror)
+}
+
+/// Swap top and bottom on `workspace`, returning its transform now.
+pub fn toggle_flip(workspace: usize) -> Transform {
+    update_transform(workspace, |t| t.flip = !t.flip)
+}
+
+/// Swap rows and columns on `workspace`, returning its transform now.
+pub fn toggle_transpose(workspace: usize) -> Transform {
+    update_transform(workspace, |t| t.transpose = !t.transpose)
+}
+
+fn transpose(region: &Region, area: &Region) -> Region {
+    Region::new(
+        area.x + (region.y - area.y),
+        area.y + (region.x - area.x),
+        region.h,
+        region.w,
+    )
+}
+
+fn mirror(region: &Region, area: &Region) -> Region {
+    let x = area.x + area.w - (region.x - area.x) - region.w;
+    Region::new(x, region.y, region.w, region.h)
+}
+
+fn flip(region: &Region, area: &Region) -> Region {
+    let y = area.y + area.h - (region.y - area.y) - region.h;
+    Region::new(region.x, y, region.w, region.h)
+}
+
+/**
+ * Run `layout` with the [Transform] of the workspace being arranged. Transposing lays
+ * out the clients on a screen with width and height swapped and then swaps the resulting
+ * regions back, turning columns into rows.
+ */
+pub fn with_transform(
+    layout: LayoutFunc,
+    clients: &[&Client],
+    focused: Option<WinId>,
+    monitor_region: &Region,
+    max_main: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    let transform = match clients.first() {
+        Some(c) => TRANSFORMS.with(|t| t.borrow().get(&c.workspace()).copied()),
+        None => None,
+    }
+    .unwrap_or_default();
+    let area = if transform.transpose {
+        transpose(monitor_region, monitor_region)
+    } else {
+        *monitor_region
+    };
+
+    layout(clients, focused, &area, max_main, ratio)
+        .into_iter()
+        .map(|(id, r)| {
+            let r = r.map(|mut r| {
+                if transform.transpose {
+                    r = transpose(&r, monitor_region);
+                }
+                if transform.mirror {
+                    r = mirror(&r, monitor_region);
+                }
+                if transform.flip {
+                    r = flip(&r, monitor_region);
+                }
+                r
+            });
+            (id, r)
+        })
+        .collect()
+}
+
+/// Wrap a [LayoutFunc] so that it is run through [with_transform].
+macro_rules! transformed {
+    ($layout:expr) => {
+        |clients: &[&penrose::core::client::Client],
+         focused: Option<penrose::core::data_types::WinId>,
+         region: &penrose::core::data_types::Region,
+         max_main: u32,
+         ratio: f32| {
+            $crate::transform::with_transform($layout, clients, focused, region, max_main, ratio)
+        }
+    };
+}
@@ -0,0 +1,243 @@
+use penrose::{
+    core::{
+        client::Client, data_types::WinId, hooks::Hook, manager::WindowManager, ring::Selector,
+        xconnection::XConn,
+    },
+    draw::{
+        bar::{Position, StatusBar},
+        widget::{ActiveWindowName, CurrentLayout, RootWindowName, Text, Workspaces},
+        Color, Draw, DrawContext, TextStyle, Widget,
+    },
+};
+
+use crate::{
+    autostart::{daemon_statuses, Status},
+    layouts::{clear_hidden_clients, hidden_clients},
+};
+
+const MAX_ACTIVE_WINDOW_CHARS: usize = 80;
+
+/**
+ * Shows how many clients of the active workspace the current layout was not able to
+ * display, e.g. when `dwindle` runs out of space.
+ */
+pub struct HiddenClients {
+    txt: Text,
+}
+
+impl HiddenClients {
+    pub fn new(style: &TextStyle) -> Self {
+        Self {
+            txt: Text::new("", style, false, false),
+        }
+    }
+
+    fn update<X: XConn>(&mut self, wm: &WindowManager<X>) {
+        let wix = wm.focused_workspaces()[wm.active_screen_index()];
+        let empty = wm
+            .workspace(&Selector::Index(wix))
+            .map(|ws| ws.is_empty())
+            .unwrap_or(true);
+        match hidden_clients(wix) {
+            n if n > 0 && !empty => self.txt.set_text(format!("+{}", n)),
+            _ => self.txt.set_text(""),
+        }
+    }
+}
+
+impl<X: XConn> Hook<X> for HiddenClients {
+    fn remove_client(&mut self, wm: &mut WindowManager<X>, _: WinId) -> penrose::Result<()> {
+        self.update(wm);
+        Ok(())
+    }
+
+    fn layout_change(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        workspace_index: usize,
+        _: usize,
+    ) -> penrose::Result<()> {
+        clear_hidden_clients(workspace_index);
+        self.update(wm);
+        Ok(())
+    }
+
+    fn layout_applied(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _: usize,
+        _: usize,
+    ) -> penrose::Result<()> {
+        self.update(wm);
+        Ok(())
+    }
+
+    fn workspace_change(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _: usize,
+        _: usize,
+    ) -> penrose::Result<()> {
+        self.update(wm);
+        Ok(())
+    }
+
+    fn screen_change(&mut self, wm: &mut WindowManager<X>, _: usize) -> penrose::Result<()> {
+        self.update(wm);
+        Ok(())
+    }
+
+    fn new_client(&mut self, wm: &mut WindowManager<X>, _: &mut Client) -> penrose::Result<()> {
+        self.update(wm);
+        Ok(())
+    }
+}
+
+impl Widget for HiddenClients {
+    fn draw(
+        &mut self,
+        ctx: &mut dyn DrawContext,
+        s: usize,
+        f: bool,
+        w: f64,
+        h: f64,
+    ) -> penrose::draw::Result<()> {
+        self.txt.draw(ctx, s, f, w, h)
+    }
+
+    fn current_extent(
+        &mut self,
+        ctx: &mut dyn DrawContext,
+        h: f64,
+    ) -> penrose::draw::Result<(f64, f64)> {
+        self.txt.current_extent(ctx, h)
+    }
+
+    fn require_draw(&self) -> bool {
+        self.txt.require_draw()
+    }
+
+    fn is_greedy(&self) -> bool {
+        false
+    }
+}
+
+/**
+ * Lists the supervised daemons that are not running, see [Command::supervise][1].
+ *
+ * [1]: crate::autostart::Command::supervise
+ */
+pub struct Daemons {
+    txt: Text,
+}
+
+impl Daemons {
+    pub fn new(style: &TextStyle) -> Self {
+        Self {
+            txt: Text::new("", style, false, false),
+        }
+    }
+}
+
+impl<X: XConn> Hook<X> for Daemons {
+    // daemons are supervised from other threads, which send an event when one changes
+    fn event_handled(&mut self, _: &mut WindowManager<X>) -> penrose::Result<()> {
+        let down: Vec<String> = daemon_statuses()
+            .into_iter()
+            .filter_map(|(name, status)| match status {
+                Status::Running { .. } => None,
+                Status::Restarting { restarts } => {
+                    Some(format!("{} restarting ({})", name, restarts))
+                }
+                Status::Failed { .. } => Some(format!("{} failed", name)),
+            })
+            .collect();
+        let text = down.join(" ");
+        if *self.txt.get_text() != text {
+            self.txt.set_text(text);
+        }
+        Ok(())
+    }
+}
+
+impl Widget for Daemons {
+    fn draw(
+        &mut self,
+        ctx: &mut dyn DrawContext,
+        s: usize,
+        f: bool,
+        w: f64,
+        h: f64,
+    ) -> penrose::draw::Result<()> {
+        self.txt.draw(ctx, s, f, w, h)
+    }
+
+    fn current_extent(
+        &mut self,
+        ctx: &mut dyn DrawContext,
+        h: f64,
+    ) -> penrose::draw::Result<(f64, f64)> {
+        self.txt.current_extent(ctx, h)
+    }
+
+    fn require_draw(&self) -> bool {
+        self.txt.require_draw()
+    }
+
+    fn is_greedy(&self) -> bool {
+        false
+    }
+}
+
+/**
+ * The `dwm_bar` from penrose with a [HiddenClients] indicator next to the layout symbol
+ * and the [Daemons] that are down before the root window name.
+ */
+pub fn status_bar<C, D, X>(
+    drw: D,
+    height: usize,
+    style: &TextStyle,
+    highlight: impl Into<Color>,
+    empty_ws: impl Into<Color>,
+    workspaces: Vec<impl Into<String>>,
+) -> penrose::draw::Result<StatusBar<C, D, X>>
+where
+    C: DrawContext + 'static,
+    D: Draw<Ctx = C>,
+    X: XConn,
+{
+    let highlight = highlight.into();
+    let workspaces: Vec<String> = workspaces.into_iter().map(|w| w.into()).collect();
+
+    StatusBar::try_new(
+        drw,
+        Position::Top,
+        height,
+        style.bg.unwrap_or_else(|| 0x000000.into()),
+        &[&style.font],
+        vec![
+            Box::new(Workspaces::new(&workspaces, style, highlight, empty_ws)),
+            Box::new(CurrentLayout::new(style)),
+            Box::new(HiddenClients::new(style)),
+            Box::new(ActiveWindowName::new(
+                &TextStyle {
+                    bg: Some(highlight),
+                    padding: (6.0, 4.0),
+                    ..style.clone()
+                },
+                MAX_ACTIVE_WINDOW_CHARS,
+                true,
+                false,
+            )),
+            Box::new(Daemons::new(style)),
+            Box::new(RootWindowName::new(
+                &TextStyle {
+                    padding: (4.0, 2.0),
+                    ..style.clone()
+                },
+                false,
+                true,
+            )),
+        ],
+    )
+}
@@ -0,0 +1,75 @@
+//! Tests for the layout state saved for every workspace.
+use penrose::core::{
+    layout::{monocle, side_stack, Layout, LayoutConf},
+    workspace::Workspace,
+};
+
+use crate::workspaces::{
+    apply, load_states, restore, save_states, state_of, LayoutSettings, WorkspaceState,
+};
+
+fn workspace(name: &str) -> Workspace {
+    Workspace::new(
+        name,
+        vec![
+            Layout::new("[side]", LayoutConf::default(), side_stack, 1, 0.6),
+            Layout::new("[mono]", LayoutConf::default(), monocle, 1, 0.6),
+        ],
+    )
+}
+
+#[test]
+fn layouts_are_read_from_workspaces() {
+    let state = state_of(&workspace("1")).unwrap();
+    assert_eq!(
+        state,
+        WorkspaceState {
+            name: "1".into(),
+            layout: "[side]".into(),
+            layouts: vec![
+                LayoutSettings::new("[side]", 1, 0.6),
+                LayoutSettings::new("[mono]", 1, 0.6),
+            ],
+        }
+    );
+}
+
+#[test]
+fn layouts_are_applied_to_workspaces() {
+    let mut ws = workspace("1");
+    apply(&mut ws, &LayoutSettings::new("[mono]", 3, 0.25)).unwrap();
+    let state = state_of(&ws).unwrap();
+    assert_eq!(state.layout, "[mono]");
+    assert_eq!(state.layouts[1], LayoutSettings::new("[mono]", 3, 0.25));
+
+    // unknown layouts are skipped
+    apply(&mut ws, &LayoutSettings::new("[gone]", 2, 0.5)).unwrap();
+    assert_eq!(state_of(&ws).unwrap(), state);
+}
+
+#[test]
+fn restored_max_main_is_bounded() {
+    let mut ws = workspace("1");
+    apply(&mut ws, &LayoutSettings::new("[side]", u32::MAX, 0.6)).unwrap();
+    assert_eq!(state_of(&ws).unwrap().layouts[0].max_main, 64);
+}
+
+#[test]
+fn layouts_are_saved_and_restored() {
+    let path = std::env::temp_dir().join(format!("penrose-workspaces-{}.toml", std::process::id()));
+    let mut ws = workspace("1");
+    apply(&mut ws, &LayoutSettings::new("[side]", 2, 0.75)).unwrap();
+    apply(&mut ws, &LayoutSettings::new("[mono]", 0, 0.4)).unwrap();
+    ws.try_set_layout("[side]");
+    let saved = vec![state_of(&ws).unwrap(), state_of(&workspace("2")).unwrap()];
+
+    save_states(&path, &saved).unwrap();
+    let loaded = load_states(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, saved);
+
+    let mut fresh = workspace("1");
+    restore(&mut fresh, &loaded[0]).unwrap();
+    assert_eq!(state_of(&fresh).unwrap(), saved[0]);
+    assert!(load_states(&path).unwrap().is_empty());
+}
@@ -0,0 +1,206 @@
+//! Per workspace layout defaults, saved to a state file so that the layout, `max_main` and
+//! `ratio` picked on every workspace survive a restart.
+use penrose::{
+    core::{
+        data_types::Change, hooks::Hook, manager::WindowManager, ring::Selector,
+        workspace::Workspace, xconnection::XConn,
+    },
+    PenroseError, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Largest `max_main` restored, penrose only changes it one client at a time
+const MAX_MAIN: u32 = 64;
+
+/// The state of one of the layouts of a workspace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutSettings {
+    pub symbol: String,
+    pub max_main: u32,
+    pub ratio: f32,
+}
+
+impl LayoutSettings {
+    pub fn new(symbol: impl Into<String>, max_main: u32, ratio: f32) -> Self {
+        Self {
+            symbol: symbol.into(),
+            max_main,
+            ratio,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WorkspaceState {
+    pub name: String,
+    /// Symbol of the active layout
+    pub layout: String,
+    pub layouts: Vec<LayoutSettings>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StateFile {
+    #[serde(default)]
+    workspace: Vec<WorkspaceState>,
+}
+
+// The layouts of a workspace are private, but penrose can serialize them. penrose is
+// pinned so that these fields don't change under us.
+#[derive(Deserialize)]
+struct LayoutRing {
+    elements: Vec<LayoutSettings>,
+    focused: usize,
+}
+
+#[derive(Deserialize)]
+struct WorkspaceView {
+    layouts: LayoutRing,
+}
+
+fn raw(e: impl ToString) -> PenroseError {
+    PenroseError::Raw(e.to_string())
+}
+
+pub(crate) fn state_of(ws: &Workspace) -> Result<WorkspaceState> {
+    let view: WorkspaceView = toml::Value::try_from(ws)
+        .map_err(raw)?
+        .try_into()
+        .map_err(raw)?;
+    let layout = view
+        .layouts
+        .elements
+        .get(view.layouts.focused)
+        .map(|l| l.symbol.clone())
+        .unwrap_or_default();
+    Ok(WorkspaceState {
+        name: ws.name().to_string(),
+        layout,
+        layouts: view.layouts.elements,
+    })
+}
+
+/// Focus the layout of `settings` on `ws` and give it the same `max_main`, up to
+/// [MAX_MAIN], and `ratio`.
+pub(crate) fn apply(ws: &mut Workspace, settings: &LayoutSettings) -> Result<()> {
+    let current = match state_of(ws)?
+        .layouts
+        .into_iter()
+        .find(|l| l.symbol == settings.symbol)
+    {
+        Some(current) => current,
+        None => return Ok(()),
+    };
+    ws.try_set_layout(&settings.symbol);
+
+    let max_main = settings.max_main.min(MAX_MAIN);
+    let (change, steps) = if max_main > current.max_main {
+        (Change::More, max_main - current.max_main)
+    } else {
+        (Change::Less, current.max_main - max_main)
+    };
+    (0..steps).for_each(|_| ws.update_max_main(change));
+    // the ratio is clamped to [0, 1], so it can be set from zero
+    ws.update_main_ratio(Change::Less, 1.0);
+    ws.update_main_ratio(Change::More, settings.ratio);
+    Ok(())
+}
+
+/// Put `ws` back in a saved `state`.
+pub(crate) fn restore(ws: &mut Workspace, state: &WorkspaceState) -> Result<()> {
+    for settings in state.layouts.iter() {
+        apply(ws, settings)?;
+    }
+    ws.try_set_layout(&state.layout);
+    Ok(())
+}
+
+pub(crate) fn load_states(path: &Path) -> Result<Vec<WorkspaceState>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let s = fs::read_to_string(path).map_err(raw)?;
+    let file: StateFile = toml::from_str(&s).map_err(raw)?;
+    Ok(file.workspace)
+}
+
+pub(crate) fn save_states(path: &Path, states: &[WorkspaceState]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(raw)?;
+    }
+    let file = StateFile {
+        workspace: states.to_vec(),
+    };
+    fs::write(path, toml::to_string(&file).map_err(raw)?).map_err(raw)
+}
+
+/**
+ * Starts every workspace on its declared default layout, or on whatever it was using
+ * when the window manager last exited, and writes the layouts of a workspace to
+ * `state_file` whenever they change.
+ */
+pub struct WorkspaceLayouts {
+    defaults: Vec<(String, LayoutSettings)>,
+    state_file: PathBuf,
+    saved: Vec<WorkspaceState>,
+}
+
+impl WorkspaceLayouts {
+    /// `defaults` are given by workspace name.
+    pub fn new(
+        defaults: Vec<(impl Into<String>, LayoutSettings)>,
+        state_file: impl Into<PathBuf>,
+    ) -> Box<Self> {
+        Box::new(Self {
+            defaults: defaults.into_iter().map(|(n, s)| (n.into(), s)).collect(),
+            state_file: state_file.into(),
+            saved: vec![],
+        })
+    }
+}
+
+impl<X: XConn> Hook<X> for WorkspaceLayouts {
+    fn startup(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+        // a broken state file shouldn't keep the defaults from being applied
+        let saved = load_states(&self.state_file).unwrap_or_default();
+
+        for ws in wm.all_workspaces_mut(&Selector::Condition(&|_| true)) {
+            let name = ws.name().to_string();
+            match saved.iter().find(|s| s.name == name) {
+                Some(state) => restore(ws, state)?,
+                None => {
+                    if let Some((_, settings)) = self.defaults.iter().find(|(n, _)| *n == name) {
+                        apply(ws, settings)?;
+                    }
+                }
+            }
+            self.saved.push(state_of(ws)?);
+        }
+
+        for screen_index in 0..wm.n_screens() {
+            wm.layout_screen(screen_index)?;
+        }
+        Ok(())
+    }
+
+    fn layout_applied(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        workspace_index: usize,
+        _: usize,
+    ) -> Result<()> {
+        let state = match wm.workspace(&Selector::Index(workspace_index)) {
+            Some(ws) => state_of(ws)?,
+            None => return Ok(()),
+        };
+        match self.saved.iter_mut().find(|s| s.name == state.name) {
+            Some(saved) if *saved == state => return Ok(()),
+            Some(saved) => *saved = state,
+            None => self.saved.push(state),
+        }
+        save_states(&self.state_file, &self.saved)
+    }
+}